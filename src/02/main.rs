@@ -21,6 +21,42 @@ mod tests {
         let input = parse_input("./src/02/test.txt");
         assert_eq!(play_with_ultra_top_secret_strategy_guide(input), 12);
     }
+
+    #[test]
+    fn tournament_against_column_opponent() {
+        let input = parse_input("./src/02/test.txt");
+        let results = tournament(&input, 10, 42);
+        let column = results.iter().find(|r| r.strategy == "column").unwrap();
+        assert_eq!(column.expected_score, 15.0);
+        assert_eq!(column.variance, 0.0);
+    }
+
+    #[test]
+    fn seeded_matches_are_reproducible() {
+        let input = parse_input("./src/02/test.txt");
+        assert_eq!(tournament(&input, 20, 7), tournament(&input, 20, 7));
+    }
+
+    #[test]
+    #[should_panic(expected = "a tournament needs at least one match")]
+    fn tournament_without_matches() {
+        let input = parse_input("./src/02/test.txt");
+        tournament(&input, 0, 7);
+    }
+
+    #[test]
+    fn adaptive_opponents_exploit_constant_guide() {
+        // Once the models have seen enough rounds they know we always play paper
+        let input = vec![('A', 'Y'); 10];
+        let lost = round_score(('C', 'Y'));
+        let won = round_score(('A', 'Y'));
+        for seed in 0..10 {
+            let mut opponent = FrequencyCounting::new(seed);
+            assert!(play_match(&input, &mut opponent) <= 9 * lost + won);
+            let mut opponent = Markov::new(seed);
+            assert!(play_match(&input, &mut opponent) <= 8 * lost + 2 * won);
+        }
+    }
 }
 
 fn parse_input(path: &str) -> Vec<(char, char)> {
@@ -90,6 +126,200 @@ fn play_with_ultra_top_secret_strategy_guide(input: Vec<(char, char)>) -> u64 {
         .sum()
 }
 
+// xorshift64*, so matches can be replayed from a seed without extra dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must never be zero
+        Rng(seed.wrapping_mul(0x9E3779B97F4A7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+const OPPONENT_MOVES: [char; 3] = ['A', 'B', 'C'];
+const OWN_MOVES: [char; 3] = ['X', 'Y', 'Z'];
+
+fn own_move_index(own: char) -> usize {
+    match own {
+        'X' => 0,
+        'Y' => 1,
+        'Z' => 2,
+        _ => panic!("invalid move"),
+    }
+}
+
+fn counter_move(own: char) -> char {
+    // The opponent move that beats what we are expected to play
+    OPPONENT_MOVES[(own_move_index(own) + 1) % 3]
+}
+
+fn most_likely(counts: &[u64; 3], rng: &mut Rng) -> char {
+    let max = *counts.iter().max().unwrap();
+    let candidates: Vec<char> = (0..3)
+        .filter(|i| counts[*i] == max)
+        .map(|i| OWN_MOVES[i])
+        .collect();
+    candidates[rng.below(candidates.len())]
+}
+
+trait Opponent {
+    fn play(&mut self, round: (char, char)) -> char;
+    fn observe(&mut self, own: char);
+}
+
+// Plays the first column of the strategy guide, like the puzzle assumes
+struct Column;
+
+impl Opponent for Column {
+    fn play(&mut self, round: (char, char)) -> char {
+        round.0
+    }
+
+    fn observe(&mut self, _own: char) {}
+}
+
+struct UniformRandom {
+    rng: Rng,
+}
+
+impl UniformRandom {
+    fn new(seed: u64) -> Self {
+        UniformRandom {
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Opponent for UniformRandom {
+    fn play(&mut self, _round: (char, char)) -> char {
+        OPPONENT_MOVES[self.rng.below(3)]
+    }
+
+    fn observe(&mut self, _own: char) {}
+}
+
+// Counters whatever we played most often so far
+struct FrequencyCounting {
+    rng: Rng,
+    counts: [u64; 3],
+}
+
+impl FrequencyCounting {
+    fn new(seed: u64) -> Self {
+        FrequencyCounting {
+            rng: Rng::new(seed),
+            counts: [0; 3],
+        }
+    }
+}
+
+impl Opponent for FrequencyCounting {
+    fn play(&mut self, _round: (char, char)) -> char {
+        counter_move(most_likely(&self.counts, &mut self.rng))
+    }
+
+    fn observe(&mut self, own: char) {
+        self.counts[own_move_index(own)] += 1;
+    }
+}
+
+// Counters the move we most often played after our previous move
+struct Markov {
+    rng: Rng,
+    transitions: [[u64; 3]; 3],
+    previous: Option<char>,
+}
+
+impl Markov {
+    fn new(seed: u64) -> Self {
+        Markov {
+            rng: Rng::new(seed),
+            transitions: [[0; 3]; 3],
+            previous: None,
+        }
+    }
+}
+
+impl Opponent for Markov {
+    fn play(&mut self, _round: (char, char)) -> char {
+        let counts = match self.previous {
+            Some(previous) => self.transitions[own_move_index(previous)],
+            None => [0; 3],
+        };
+        counter_move(most_likely(&counts, &mut self.rng))
+    }
+
+    fn observe(&mut self, own: char) {
+        if let Some(previous) = self.previous {
+            self.transitions[own_move_index(previous)][own_move_index(own)] += 1;
+        }
+        self.previous = Some(own);
+    }
+}
+
+fn play_match(input: &[(char, char)], opponent: &mut dyn Opponent) -> u64 {
+    input
+        .iter()
+        .map(|round| {
+            let opponent_move = opponent.play(*round);
+            opponent.observe(round.1);
+            round_score((opponent_move, round.1))
+        })
+        .sum()
+}
+
+#[derive(Debug, PartialEq)]
+struct TournamentResult {
+    strategy: &'static str,
+    expected_score: f64,
+    variance: f64,
+}
+
+type NewOpponent = fn(u64) -> Box<dyn Opponent>;
+
+fn tournament(input: &[(char, char)], matches: u64, seed: u64) -> Vec<TournamentResult> {
+    assert!(matches > 0, "a tournament needs at least one match");
+    let strategies: [(&'static str, NewOpponent); 4] = [
+        ("column", |_| Box::new(Column)),
+        ("uniform random", |seed| Box::new(UniformRandom::new(seed))),
+        ("frequency counting", |seed| {
+            Box::new(FrequencyCounting::new(seed))
+        }),
+        ("markov", |seed| Box::new(Markov::new(seed))),
+    ];
+
+    strategies
+        .iter()
+        .map(|(strategy, new_opponent)| {
+            let scores: Vec<f64> = (0..matches)
+                .map(|i| play_match(input, new_opponent(seed.wrapping_add(i)).as_mut()) as f64)
+                .collect();
+            let expected_score = scores.iter().sum::<f64>() / matches as f64;
+            let variance = scores
+                .iter()
+                .map(|score| (score - expected_score).powi(2))
+                .sum::<f64>()
+                / matches as f64;
+            TournamentResult {
+                strategy,
+                expected_score,
+                variance,
+            }
+        })
+        .collect()
+}
+
 fn main() {
     println!("Hello, day 2!");
 
@@ -99,4 +329,11 @@ fn main() {
 
     let score = play_with_ultra_top_secret_strategy_guide(input.clone());
     println!("Part 2: {}", score);
+
+    for result in tournament(&input, 100, 2022) {
+        println!(
+            "Tournament vs {}: expected score {:.1}, variance {:.1}",
+            result.strategy, result.expected_score, result.variance
+        );
+    }
 }
@@ -7,9 +7,10 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_find_duplicate_item() {
+    fn test_find_duplicate_items() {
         let rucksack = "vJrwpWtwJgWrhcsFMMfFFhFp";
-        assert_eq!(find_duplicate_item(rucksack), 'p');
+        assert_eq!(find_duplicate_items(rucksack), vec!['p']);
+        assert_eq!(find_duplicate_items("abcCba"), vec!['a', 'b']);
     }
 
     #[test]
@@ -20,6 +21,19 @@ mod tests {
         assert_eq!(priority('Z'), 52);
     }
 
+    #[test]
+    fn test_item_masks() {
+        let mask = item_mask("aZza");
+        assert_eq!(mask.count_ones(), 3);
+        assert_eq!(mask_items(mask), vec!['a', 'z', 'Z']);
+    }
+
+    #[test]
+    fn test_find_badges() {
+        assert_eq!(find_badges(&["abc", "bcd", "cbe", "xcb"]), vec!['b', 'c']);
+        assert_eq!(find_badges(&["abc", "def"]), vec![]);
+    }
+
     #[test]
     fn part_one() {
         let input = fs::read_to_string("./src/03/test.txt").unwrap();
@@ -31,9 +45,57 @@ mod tests {
     #[test]
     fn part_two() {
         let input = fs::read_to_string("./src/03/test.txt").unwrap();
-        let groups = parse_input_as_groups(&input);
+        let groups = parse_input_as_groups(&input, 3);
+
+        assert_eq!(sum_of_group_badges(&groups), 70);
+    }
 
-        assert_eq!(sum_of_group_badges(groups), 70);
+    #[test]
+    fn groups_of_other_sizes() {
+        let input = fs::read_to_string("./src/03/test.txt").unwrap();
+        let groups = parse_input_as_groups(&input, 4);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].len(), 2);
+        assert_eq!(find_badges(&groups[0]), vec![]);
+        assert_eq!(find_badges(&groups[1]), vec!['G', 'J', 'Z']);
+    }
+
+    #[test]
+    fn audit_valid_input() {
+        let input = fs::read_to_string("./src/03/test.txt").unwrap();
+        assert_eq!(audit(&input, 3), vec![]);
+    }
+
+    #[test]
+    fn audit_reports_violations() {
+        let input = "abca\nab1Cab\nabcab\nabAB\nabcabc\nxyzxyz\nqq";
+        assert_eq!(
+            audit(input, 3),
+            vec![
+                Violation::InvalidItems {
+                    line: 2,
+                    items: vec!['1']
+                },
+                Violation::OddLength { line: 3, length: 5 },
+                Violation::NoSharedItem { line: 4 },
+                Violation::MultipleSharedItems {
+                    line: 5,
+                    items: vec!['a', 'b', 'c']
+                },
+                Violation::MultipleSharedItems {
+                    line: 6,
+                    items: vec!['x', 'y', 'z']
+                },
+                Violation::MultipleBadges {
+                    lines: vec![1, 2, 3],
+                    items: vec!['a', 'b']
+                },
+                Violation::NoBadge {
+                    lines: vec![4, 5, 6]
+                },
+                Violation::IncompleteGroup { lines: vec![7] },
+            ]
+        );
     }
 }
 
@@ -41,25 +103,14 @@ fn parse_input(input: &str) -> Vec<&str> {
     input.lines().collect()
 }
 
-fn parse_input_as_groups(input: &str) -> Vec<(&str, &str, &str)> {
-    let groups = input
+fn parse_input_as_groups(input: &str, group_size: usize) -> Vec<Vec<&str>> {
+    // The last group keeps whatever rucksacks are left over
+    input
         .lines()
-        .chunks(3)
+        .chunks(group_size)
         .into_iter()
-        .map(|mut x| (x.next().unwrap(), x.next().unwrap(), x.next().unwrap()))
-        .collect();
-    groups
-}
-
-fn find_duplicate_item(rucksack: &str) -> char {
-    let (one, two) = rucksack.split_at(rucksack.len() / 2);
-
-    for item in one.chars() {
-        if two.contains(item) {
-            return item;
-        }
-    }
-    panic!("no duplicate found")
+        .map(|group| group.collect())
+        .collect()
 }
 
 fn priority(item: char) -> u8 {
@@ -71,41 +122,208 @@ fn priority(item: char) -> u8 {
     }
 }
 
-fn find_badge(group: (&str, &str, &str)) -> char {
-    // Loop through the items in the first rucksack and see if they exist in the other two
-    for item in group.0.chars() {
-        if group.1.contains(item) && group.2.contains(item) {
-            return item;
-        }
+fn item(priority: u8) -> char {
+    match priority {
+        1..=26 => (priority - 1 + 97) as char,
+        27..=52 => (priority - 27 + 65) as char,
+        _ => panic!("invalid priority"),
     }
-    panic!("could not find badge")
 }
 
-fn sum_of_group_badges(groups: Vec<(&str, &str, &str)>) -> u64 {
+// Bit n is set when the item with priority n is present
+fn item_mask(items: &str) -> u64 {
+    items
+        .chars()
+        .fold(0, |mask, item| mask | (1 << priority(item)))
+}
+
+fn mask_items(mask: u64) -> Vec<char> {
+    (1..=52)
+        .filter(|priority| mask & (1 << priority) != 0)
+        .map(item)
+        .collect()
+}
+
+fn compartment_masks(rucksack: &str) -> (u64, u64) {
+    let (one, two) = rucksack.split_at(rucksack.len() / 2);
+    (item_mask(one), item_mask(two))
+}
+
+fn find_duplicate_items(rucksack: &str) -> Vec<char> {
+    let (one, two) = compartment_masks(rucksack);
+    mask_items(one & two)
+}
+
+fn group_mask(group: &[&str]) -> u64 {
+    group
+        .iter()
+        .map(|rucksack| item_mask(rucksack))
+        .reduce(|common, mask| common & mask)
+        .unwrap_or(0)
+}
+
+fn find_badges(group: &[&str]) -> Vec<char> {
+    mask_items(group_mask(group))
+}
+
+fn sum_of_group_badges(groups: &[Vec<&str>]) -> u64 {
     groups
         .iter()
-        .map(|group| find_badge(*group))
+        .flat_map(|group| find_badges(group))
         .map(|badge| priority(badge) as u64)
         .sum()
 }
 
-fn sum_of_priorities(rucksacks: &Vec<&str>) -> u64 {
+fn sum_of_priorities(rucksacks: &[&str]) -> u64 {
     rucksacks
         .iter()
-        .map(|x| find_duplicate_item(*x))
-        .map(|x| priority(x) as u64)
+        .flat_map(|rucksack| find_duplicate_items(rucksack))
+        .map(|item| priority(item) as u64)
         .sum()
 }
 
+#[derive(Debug, PartialEq)]
+enum Violation {
+    InvalidItems { line: usize, items: Vec<char> },
+    OddLength { line: usize, length: usize },
+    NoSharedItem { line: usize },
+    MultipleSharedItems { line: usize, items: Vec<char> },
+    NoBadge { lines: Vec<usize> },
+    MultipleBadges { lines: Vec<usize>, items: Vec<char> },
+    IncompleteGroup { lines: Vec<usize> },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::InvalidItems { line, items } => {
+                write!(f, "line {}: invalid items {:?}", line, items)
+            }
+            Violation::OddLength { line, length } => {
+                write!(f, "line {}: odd number of items ({})", line, length)
+            }
+            Violation::NoSharedItem { line } => {
+                write!(f, "line {}: compartments share no item", line)
+            }
+            Violation::MultipleSharedItems { line, items } => {
+                write!(f, "line {}: compartments share {:?}", line, items)
+            }
+            Violation::NoBadge { lines } => write!(f, "lines {:?}: group has no badge", lines),
+            Violation::MultipleBadges { lines, items } => {
+                write!(
+                    f,
+                    "lines {:?}: group has badge candidates {:?}",
+                    lines, items
+                )
+            }
+            Violation::IncompleteGroup { lines } => {
+                write!(f, "lines {:?}: incomplete group", lines)
+            }
+        }
+    }
+}
+
+fn is_item(item: char) -> bool {
+    item.is_ascii_alphabetic()
+}
+
+// Like item_mask, but skips anything that is not an item instead of panicking
+fn valid_item_mask(items: &str) -> u64 {
+    items
+        .chars()
+        .filter(|item| is_item(*item))
+        .fold(0, |mask, item| mask | (1 << priority(item)))
+}
+
+fn audit_rucksack(line: usize, rucksack: &str) -> Vec<Violation> {
+    let mut violations = vec![];
+
+    let invalid_items: Vec<char> = rucksack.chars().filter(|item| !is_item(*item)).collect();
+    if !invalid_items.is_empty() {
+        violations.push(Violation::InvalidItems {
+            line,
+            items: invalid_items,
+        });
+        return violations;
+    }
+
+    if !rucksack.len().is_multiple_of(2) {
+        violations.push(Violation::OddLength {
+            line,
+            length: rucksack.len(),
+        });
+        return violations;
+    }
+
+    match find_duplicate_items(rucksack).as_slice() {
+        [] => violations.push(Violation::NoSharedItem { line }),
+        [_] => {}
+        items => violations.push(Violation::MultipleSharedItems {
+            line,
+            items: items.to_vec(),
+        }),
+    }
+    violations
+}
+
+fn audit_group(lines: Vec<usize>, group: &[&str], group_size: usize) -> Option<Violation> {
+    if group.len() < group_size {
+        return Some(Violation::IncompleteGroup { lines });
+    }
+
+    let common = group
+        .iter()
+        .map(|rucksack| valid_item_mask(rucksack))
+        .fold(u64::MAX, |common, mask| common & mask);
+    match mask_items(common).as_slice() {
+        [] => Some(Violation::NoBadge { lines }),
+        [_] => None,
+        items => Some(Violation::MultipleBadges {
+            lines,
+            items: items.to_vec(),
+        }),
+    }
+}
+
+fn audit(input: &str, group_size: usize) -> Vec<Violation> {
+    let rucksacks = parse_input(input);
+    let mut violations: Vec<Violation> = rucksacks
+        .iter()
+        .enumerate()
+        .flat_map(|(index, rucksack)| audit_rucksack(index + 1, rucksack))
+        .collect();
+
+    for (index, group) in parse_input_as_groups(input, group_size).iter().enumerate() {
+        let first_line = index * group_size + 1;
+        let lines = (first_line..first_line + group.len()).collect();
+        violations.extend(audit_group(lines, group, group_size));
+    }
+    violations
+}
+
 fn main() {
     println!("Hello, day 3!");
 
     let input = fs::read_to_string("./input/03/input.txt").unwrap();
+
+    // Audit first, the sums panic on invalid items
+    let violations = audit(&input, 3);
+    println!("Audit: {} violations", violations.len());
+    for violation in &violations {
+        println!("  {}", violation);
+    }
+    if violations
+        .iter()
+        .any(|violation| matches!(violation, Violation::InvalidItems { .. }))
+    {
+        return;
+    }
+
     let rucksacks = parse_input(&input);
     let sum = sum_of_priorities(&rucksacks);
     println!("Part 1: {}", sum);
 
-    let groups = parse_input_as_groups(&input);
-    let sum = sum_of_group_badges(groups);
+    let groups = parse_input_as_groups(&input, 3);
+    let sum = sum_of_group_badges(&groups);
     println!("Part 2: {}", sum);
 }
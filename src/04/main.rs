@@ -7,10 +7,10 @@ mod tests {
     #[test]
     fn test_input_parsing() {
         let pairs = parse_input("./src/04/test.txt");
-        assert_eq!(pairs[0].0 .0, 2);
-        assert_eq!(pairs[0].0 .1, 4);
-        assert_eq!(pairs[0].1 .0, 6);
-        assert_eq!(pairs[0].1 .1, 8);
+        assert_eq!(pairs[0].0.start, 2);
+        assert_eq!(pairs[0].0.end, 4);
+        assert_eq!(pairs[0].1.start, 6);
+        assert_eq!(pairs[0].1.end, 8);
     }
 
     #[test]
@@ -24,20 +24,140 @@ mod tests {
         let input = parse_input("./src/04/test.txt");
         assert_eq!(count_partial_overlap(input), 4);
     }
+
+    #[test]
+    fn interval_algebra() {
+        let a = Interval::new(2, 6);
+        let b = Interval::new(4, 8);
+        let c = Interval::new(9, 9);
+        assert_eq!(a.length(), 5);
+        assert_eq!(a.intersection(&b), Some(Interval::new(4, 6)));
+        assert_eq!(a.intersection(&c), None);
+        assert_eq!(a.union(&b), vec![Interval::new(2, 8)]);
+        assert_eq!(b.union(&c), vec![Interval::new(4, 9)]);
+        assert_eq!(a.union(&c), vec![a, c]);
+        assert_eq!(a.difference(&b), vec![Interval::new(2, 3)]);
+        assert_eq!(
+            Interval::new(1, 9).difference(&Interval::new(4, 5)),
+            vec![Interval::new(1, 3), Interval::new(6, 9)]
+        );
+        assert_eq!(b.difference(&Interval::new(1, 9)), vec![]);
+        assert_eq!(
+            merge(&[c, b, Interval::new(20, 21), a]),
+            vec![Interval::new(2, 9), Interval::new(20, 21)]
+        );
+    }
+
+    #[test]
+    fn interval_tree_queries() {
+        let elves = elves(&parse_input("./src/04/test.txt"));
+        let tree = IntervalTree::new(&elves);
+        assert_eq!(tree.covering(6), vec![1, 4, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(tree.covering(1), vec![]);
+        assert_eq!(tree.covering(9), vec![5]);
+        assert_eq!(tree.max_coverage(), Some((8, 6)));
+    }
+
+    #[test]
+    fn camp_coverage() {
+        let elves = elves(&parse_input("./src/04/test.txt"));
+        assert_eq!(
+            uncovered_sections(&elves, Interval::new(1, 10)),
+            vec![Interval::new(1, 1), Interval::new(10, 10)]
+        );
+        assert_eq!(redundant_sections(&elves), vec![Interval::new(2, 8)]);
+        assert_eq!(minimal_crew(&elves), vec![5, 6]);
+    }
+
+    #[test]
+    fn minimal_crew_across_gaps() {
+        let elves = [
+            Interval::new(1, 3),
+            Interval::new(2, 5),
+            Interval::new(4, 6),
+            Interval::new(1, 4),
+            Interval::new(10, 12),
+            Interval::new(11, 11),
+        ];
+        assert_eq!(minimal_crew(&elves), vec![2, 3, 4]);
+    }
 }
 
-type Pair = ((i64, i64), (i64, i64));
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Interval {
+    start: i64,
+    end: i64, // inclusive
+}
 
-fn parse_range(input: &str) -> (i64, i64) {
+impl Interval {
+    fn new(start: i64, end: i64) -> Self {
+        Interval { start, end }
+    }
+
+    fn length(&self) -> i64 {
+        self.end - self.start + 1
+    }
+
+    fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && self.end >= other.end
+    }
+
+    fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    fn intersection(&self, other: &Interval) -> Option<Interval> {
+        if self.overlaps(other) {
+            Some(Interval::new(
+                self.start.max(other.start),
+                self.end.min(other.end),
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn union(&self, other: &Interval) -> Vec<Interval> {
+        // Sections are discrete, so adjacent intervals merge as well
+        let (first, second) = if self.start <= other.start {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        if second.start <= first.end + 1 {
+            vec![Interval::new(first.start, first.end.max(second.end))]
+        } else {
+            vec![*first, *second]
+        }
+    }
+
+    fn difference(&self, other: &Interval) -> Vec<Interval> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        let mut remaining = vec![];
+        if self.start < other.start {
+            remaining.push(Interval::new(self.start, other.start - 1));
+        }
+        if self.end > other.end {
+            remaining.push(Interval::new(other.end + 1, self.end));
+        }
+        remaining
+    }
+}
+
+type Pair = (Interval, Interval);
+
+fn parse_range(input: &str) -> Interval {
     let mut bounds = input.split('-');
-    (
+    Interval::new(
         bounds.next().unwrap().parse().unwrap(),
         bounds.next().unwrap().parse().unwrap(),
     )
 }
 
 fn parse_input(path: &str) -> Vec<Pair> {
-    std::fs::read_to_string(path)
+    fs::read_to_string(path)
         .unwrap()
         .lines()
         .map(|line| {
@@ -51,9 +171,7 @@ fn parse_input(path: &str) -> Vec<Pair> {
 }
 
 fn is_enclosed(pair: Pair) -> bool {
-    let a = pair.0 .0 >= pair.1 .0 && pair.0 .1 <= pair.1 .1;
-    let b = pair.0 .0 <= pair.1 .0 && pair.0 .1 >= pair.1 .1;
-    a || b
+    pair.0.contains(&pair.1) || pair.1.contains(&pair.0)
 }
 
 fn count_fully_enclosed(input: Vec<Pair>) -> usize {
@@ -61,15 +179,220 @@ fn count_fully_enclosed(input: Vec<Pair>) -> usize {
 }
 
 fn overlaps(pair: Pair) -> bool {
-    let first_is_beyond_second = pair.0 .0 > pair.1 .1;
-    let second_is_beyond_first = pair.1 .0 > pair.0 .1;
-    !(first_is_beyond_second || second_is_beyond_first)
+    pair.0.overlaps(&pair.1)
 }
 
 fn count_partial_overlap(input: Vec<Pair>) -> usize {
     input.iter().filter(|pair| overlaps(**pair)).count()
 }
 
+// Sorted, non-overlapping intervals covering the same sections
+fn merge(intervals: &[Interval]) -> Vec<Interval> {
+    let mut sorted = intervals.to_vec();
+    sorted.sort_by_key(|x| x.start);
+    let mut merged: Vec<Interval> = vec![];
+    for interval in sorted {
+        match merged.pop() {
+            Some(last) => merged.extend(last.union(&interval)),
+            None => merged.push(interval),
+        }
+    }
+    merged
+}
+
+// Elf 2n and 2n + 1 make up pair n
+fn elves(pairs: &[Pair]) -> Vec<Interval> {
+    pairs.iter().flat_map(|pair| [pair.0, pair.1]).collect()
+}
+
+// Sections within the span that no elf covers
+fn uncovered_sections(elves: &[Interval], span: Interval) -> Vec<Interval> {
+    merge(elves).iter().fold(vec![span], |remaining, covered| {
+        remaining
+            .iter()
+            .flat_map(|x| x.difference(covered))
+            .collect()
+    })
+}
+
+// Sections covered by more than one elf
+fn redundant_sections(elves: &[Interval]) -> Vec<Interval> {
+    let mut events: Vec<(i64, i64)> = elves
+        .iter()
+        .flat_map(|x| [(x.start, 1), (x.end + 1, -1)])
+        .collect();
+    events.sort();
+
+    let mut redundant = vec![];
+    let mut count = 0;
+    let mut start = None;
+    for (section, delta) in events {
+        count += delta;
+        match start {
+            None if count > 1 => start = Some(section),
+            Some(first) if count <= 1 => {
+                redundant.push(Interval::new(first, section - 1));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    merge(&redundant)
+}
+
+// Smallest set of elves that still covers every covered section. Greedily taking the
+// assignment that reaches furthest from the first uncovered section is optimal.
+fn minimal_crew(elves: &[Interval]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..elves.len()).collect();
+    order.sort_by_key(|i| elves[*i].start);
+
+    let mut crew = vec![];
+    let mut next = i64::MIN; // first section not covered by the crew yet
+    let mut i = 0;
+    while i < order.len() {
+        if elves[order[i]].end < next {
+            i += 1;
+            continue;
+        }
+        next = next.max(elves[order[i]].start);
+
+        let mut best = order[i];
+        while i < order.len() && elves[order[i]].start <= next {
+            if elves[order[i]].end > elves[best].end {
+                best = order[i];
+            }
+            i += 1;
+        }
+        crew.push(best);
+        next = elves[best].end + 1;
+    }
+    crew.sort();
+    crew
+}
+
+// Centered interval tree: every node keeps the intervals that contain its center
+struct IntervalTree {
+    center: i64,
+    by_start: Vec<(Interval, usize)>,
+    by_end: Vec<(Interval, usize)>,
+    left: Option<Box<IntervalTree>>,
+    right: Option<Box<IntervalTree>>,
+}
+
+impl IntervalTree {
+    fn new(intervals: &[Interval]) -> Self {
+        let indexed = intervals.iter().copied().enumerate().map(|(i, x)| (x, i));
+        Self::build(indexed.collect()).unwrap_or(IntervalTree {
+            center: 0,
+            by_start: vec![],
+            by_end: vec![],
+            left: None,
+            right: None,
+        })
+    }
+
+    fn build(intervals: Vec<(Interval, usize)>) -> Option<Self> {
+        if intervals.is_empty() {
+            return None;
+        }
+        let mut endpoints: Vec<i64> = intervals
+            .iter()
+            .flat_map(|(x, _)| [x.start, x.end])
+            .collect();
+        endpoints.sort();
+        let center = endpoints[endpoints.len() / 2];
+
+        let mut left = vec![];
+        let mut right = vec![];
+        let mut here = vec![];
+        for (interval, id) in intervals {
+            if interval.end < center {
+                left.push((interval, id));
+            } else if interval.start > center {
+                right.push((interval, id));
+            } else {
+                here.push((interval, id));
+            }
+        }
+
+        let mut by_start = here.clone();
+        by_start.sort_by_key(|(x, _)| x.start);
+        let mut by_end = here;
+        by_end.sort_by_key(|(x, _)| std::cmp::Reverse(x.end));
+
+        Some(IntervalTree {
+            center,
+            by_start,
+            by_end,
+            left: Self::build(left).map(Box::new),
+            right: Self::build(right).map(Box::new),
+        })
+    }
+
+    fn collect_covering(&self, section: i64, ids: &mut Vec<usize>) {
+        if section < self.center {
+            ids.extend(
+                self.by_start
+                    .iter()
+                    .take_while(|(x, _)| x.start <= section)
+                    .map(|(_, id)| *id),
+            );
+            if let Some(left) = &self.left {
+                left.collect_covering(section, ids);
+            }
+        } else {
+            ids.extend(
+                self.by_end
+                    .iter()
+                    .take_while(|(x, _)| x.end >= section)
+                    .map(|(_, id)| *id),
+            );
+            if section > self.center {
+                if let Some(right) = &self.right {
+                    right.collect_covering(section, ids);
+                }
+            }
+        }
+    }
+
+    // Indices of all intervals that contain the section, in ascending order
+    fn covering(&self, section: i64) -> Vec<usize> {
+        let mut ids = vec![];
+        self.collect_covering(section, &mut ids);
+        ids.sort();
+        ids
+    }
+
+    fn intervals(&self) -> Vec<Interval> {
+        let mut intervals: Vec<Interval> = self.by_start.iter().map(|(x, _)| *x).collect();
+        for child in [&self.left, &self.right].into_iter().flatten() {
+            intervals.extend(child.intervals());
+        }
+        intervals
+    }
+
+    // Highest number of intervals sharing a section, and the lowest such section
+    fn max_coverage(&self) -> Option<(usize, i64)> {
+        let mut events: Vec<(i64, i64)> = self
+            .intervals()
+            .iter()
+            .flat_map(|x| [(x.start, 1), (x.end + 1, -1)])
+            .collect();
+        // At equal positions the intervals that ended are removed first
+        events.sort();
+
+        let mut best: Option<(usize, i64)> = None;
+        let mut count = 0;
+        for (section, delta) in events {
+            count += delta;
+            if best.is_none_or(|(max, _)| count as usize > max) {
+                best = Some((count as usize, section));
+            }
+        }
+        best
+    }
+}
+
 fn main() {
     println!("Hello, day 4!");
 
@@ -77,6 +400,44 @@ fn main() {
     let count = count_fully_enclosed(input.clone());
     println!("Part 1: {}", count);
 
-    let count = count_partial_overlap(input);
+    let count = count_partial_overlap(input.clone());
     println!("Part 2: {}", count);
+
+    let shared: i64 = input
+        .iter()
+        .filter_map(|pair| pair.0.intersection(&pair.1))
+        .map(|x| x.length())
+        .sum();
+    let single: i64 = input
+        .iter()
+        .flat_map(|pair| [pair.0.difference(&pair.1), pair.1.difference(&pair.0)])
+        .flatten()
+        .map(|x| x.length())
+        .sum();
+    println!("Sections cleaned twice within a pair: {}", shared);
+    println!("Sections cleaned once within a pair: {}", single);
+
+    let elves = elves(&input);
+    let covered: i64 = merge(&elves).iter().map(|x| x.length()).sum();
+    println!("Sections covered by the camp: {}", covered);
+    let camp = merge(&elves);
+    let span = Interval::new(camp[0].start, camp[camp.len() - 1].end);
+    let uncovered: i64 = uncovered_sections(&elves, span)
+        .iter()
+        .map(|x| x.length())
+        .sum();
+    let redundant: i64 = redundant_sections(&elves).iter().map(|x| x.length()).sum();
+    println!("Sections nobody covers: {}", uncovered);
+    println!("Sections covered redundantly: {}", redundant);
+    println!(
+        "Minimal cleaning crew: {} of {} elves",
+        minimal_crew(&elves).len(),
+        elves.len()
+    );
+
+    let tree = IntervalTree::new(&elves);
+    println!("Section 57 is covered by {} elves", tree.covering(57).len());
+    if let Some((count, section)) = tree.max_coverage() {
+        println!("At most {} elves cover section {}", count, section);
+    }
 }
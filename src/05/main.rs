@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
 
     #[test]
     fn test_input_parsing() {
@@ -31,6 +32,273 @@ mod tests {
         let arranged = arrange_with_crate_mover_9001(stacks, moves);
         assert_eq!(get_top_crates(arranged), "MCD");
     }
+
+    #[test]
+    fn capacity_limited_crane() {
+        let stacks = vec![vec!['A', 'B', 'C', 'D', 'E'], vec![]];
+        let moves = [Move {
+            number: 5,
+            from: 1,
+            to: 2,
+        }];
+        let arranged = arrange(stacks, &moves, &mut CapacityLimitedCrane { capacity: 2 });
+        assert_eq!(arranged, vec![vec![], vec!['D', 'E', 'B', 'C', 'A']]);
+    }
+
+    #[test]
+    #[should_panic(expected = "crane capacity must be at least 1")]
+    fn crane_without_capacity() {
+        let moves = [Move {
+            number: 1,
+            from: 1,
+            to: 2,
+        }];
+        arrange(
+            vec![vec!['A'], vec![]],
+            &moves,
+            &mut CapacityLimitedCrane { capacity: 0 },
+        );
+    }
+
+    #[test]
+    fn pair_flipping_crane() {
+        let stacks = vec![vec!['A', 'B', 'C', 'D', 'E'], vec!['X']];
+        let moves = [Move {
+            number: 5,
+            from: 1,
+            to: 2,
+        }];
+        let arranged = arrange(stacks.clone(), &moves, &mut PairFlippingCrane);
+        assert_eq!(arranged, vec![vec![], vec!['X', 'B', 'A', 'D', 'C', 'E']]);
+
+        let moves = [Move {
+            number: 2,
+            from: 1,
+            to: 1,
+        }];
+        assert_eq!(
+            arrange(stacks.clone(), &moves, &mut PairFlippingCrane),
+            stacks
+        );
+    }
+
+    #[test]
+    fn dual_crane() {
+        let (stacks, moves) = parse_input("./src/05/test.txt");
+        let mut crane = DualCrane::new(CrateMover9000, CrateMover9001);
+        let arranged = arrange(stacks.clone(), &moves, &mut crane);
+        assert_eq!(
+            arranged,
+            vec![vec!['C'], vec!['M'], vec!['P', 'Z', 'N', 'D']]
+        );
+
+        let mut crane = DualCrane::new(CrateMover9000, CrateMover9000);
+        let arranged = arrange(stacks, &moves, &mut crane);
+        assert_eq!(get_top_crates(arranged), "CMZ");
+    }
+
+    #[test]
+    fn ragged_drawing_with_named_stacks() {
+        let input = "[X]\n[Y]             [Q]\n[Z] [W]         [R]\n A   B  ten  11  foo";
+        let drawing = parse_stacks(input).unwrap();
+        assert_eq!(drawing.labels, vec!["A", "B", "ten", "11", "foo"]);
+        assert_eq!(
+            drawing.stacks,
+            vec![
+                vec!['Z', 'Y', 'X'],
+                vec!['W'],
+                vec![],
+                vec![],
+                vec!['R', 'Q']
+            ]
+        );
+
+        let moves = parse_moves(
+            "move 2 from A to ten\nmove 1 from foo to 11",
+            &drawing.labels,
+            6,
+        );
+        assert_eq!(
+            moves.unwrap(),
+            vec![
+                Move {
+                    number: 2,
+                    from: 1,
+                    to: 3
+                },
+                Move {
+                    number: 1,
+                    from: 5,
+                    to: 4
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn many_stacks() {
+        let mut crates = (0..12).map(|i| format!("[{}]", (b'A' + i) as char));
+        let mut labels = (1..=12).map(|i| format!("{:^3}", i));
+        let input = format!("{}\n{}", crates.join(" "), labels.join(" "));
+        let stacks = parse_stacks(&input).unwrap().stacks;
+        assert_eq!(stacks.len(), 12);
+        assert_eq!(stacks[11], vec!['L']);
+    }
+
+    #[test]
+    fn journal_undo_and_redo() {
+        let (stacks, moves) = parse_input("./src/05/test.txt");
+        let mut executor = Executor::new(stacks.clone());
+        executor.run(&moves, &mut CrateMover9000).unwrap();
+        let arranged = executor.stacks.clone();
+        assert_eq!(get_top_crates(arranged.clone()), "CMZ");
+
+        assert!(executor.undo());
+        assert!(executor.undo());
+        assert!(executor.redo());
+        assert!(executor.redo());
+        assert!(!executor.redo());
+        assert_eq!(executor.stacks, arranged);
+
+        executor.rewind();
+        assert_eq!(executor.stacks, stacks);
+        assert!(!executor.undo());
+
+        // A new move replaces whatever could be redone
+        executor.apply(&moves[0], &mut CrateMover9001).unwrap();
+        assert!(!executor.redo());
+        assert_eq!(executor.journal.len(), 1);
+    }
+
+    #[test]
+    fn invalid_moves() {
+        let (stacks, mut moves) = parse_input("./src/05/test.txt");
+        moves[2].number = 3;
+        let mut executor = Executor::new(stacks.clone());
+        assert_eq!(
+            executor.run(&moves, &mut CrateMover9000),
+            Err(
+                "move 3: cannot take 3 crates from stack 2 holding 2 (Move { number: 3, from: 2, to: 1 })"
+                    .to_string()
+            )
+        );
+        assert_eq!(executor.applied, 2);
+
+        let mut executor = Executor::new(stacks);
+        let mov = Move {
+            number: 1,
+            from: 4,
+            to: 1,
+        };
+        assert_eq!(
+            executor.apply(&mov, &mut CrateMover9000),
+            Err("move 1: there is no stack 4 (Move { number: 1, from: 4, to: 1 })".to_string())
+        );
+    }
+
+    #[test]
+    fn render_round_trip() {
+        let input = std::fs::read_to_string("./src/05/test.txt").unwrap();
+        let drawing_text = input.split("\n\n").next().unwrap();
+        let drawing = parse_stacks(drawing_text).unwrap();
+        assert_eq!(
+            render_stacks(&drawing.stacks, &drawing.labels),
+            drawing_text
+        );
+
+        let input = "[X]\n[Y]             [Q]\n[Z] [W]         [R]\n A   B  ten  11  foo";
+        let drawing = parse_stacks(input).unwrap();
+        let rendered = render_stacks(&drawing.stacks, &drawing.labels);
+        assert_eq!(parse_stacks(&rendered).unwrap().stacks, drawing.stacks);
+    }
+
+    #[test]
+    fn frames() {
+        let (stacks, moves) = parse_input("./src/05/test.txt");
+        let mut crane = CrateMover9001;
+        let frames: Vec<String> = Frames::new(stacks, &moves, &mut crane).collect();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(
+            frames[0],
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 "
+        );
+        assert_eq!(
+            frames[3],
+            "        [D]\n        [N]\n        [Z]\n[M] [C] [P]\n 1   2   3 "
+        );
+    }
+
+    #[test]
+    fn ropes_match_cranes() {
+        let (stacks, moves) = parse_input("./src/05/test.txt");
+        let arranged = arrange_with_ropes(&stacks, &moves, true);
+        assert_eq!(get_top_crates(arranged), "CMZ");
+        let arranged = arrange_with_ropes(&stacks, &moves, false);
+        assert_eq!(get_top_crates(arranged), "MCD");
+
+        let (stacks, moves) = generate_input(5, 50, 1000);
+        assert!(moves.iter().any(|mov| mov.from == mov.to));
+        assert_eq!(
+            arrange_with_ropes(&stacks, &moves, true),
+            arrange(stacks.clone(), &moves, &mut CrateMover9000)
+        );
+        assert_eq!(
+            arrange_with_ropes(&stacks, &moves, false),
+            arrange(stacks.clone(), &moves, &mut CrateMover9001)
+        );
+    }
+
+    #[test]
+    fn planner() {
+        let (stacks, moves) = parse_input("./src/05/test.txt");
+        for crane in [
+            &mut CrateMover9000 as &mut dyn Crane,
+            &mut CrateMover9001 as &mut dyn Crane,
+        ] {
+            let target = arrange(stacks.clone(), &moves, crane);
+            let plan = plan_moves(&stacks, &target, crane, moves.len()).unwrap();
+            assert!(plan.len() <= moves.len());
+            assert_eq!(arrange(stacks.clone(), &plan, crane), target);
+        }
+
+        // The 9001 moves the whole stack in one go, the 9000 has to reverse it twice
+        let stacks = vec![vec!['A', 'B', 'C'], vec![], vec![]];
+        let target = vec![vec![], vec!['A', 'B', 'C'], vec![]];
+        let plan = plan_moves(&stacks, &target, &mut CrateMover9001, 5).unwrap();
+        assert_eq!(plan.len(), 1);
+        let plan = plan_moves(&stacks, &target, &mut CrateMover9000, 5).unwrap();
+        assert_eq!(plan.len(), 2);
+
+        let impossible = vec![vec![], vec!['A', 'B', 'X'], vec![]];
+        assert_eq!(
+            plan_moves(&stacks, &impossible, &mut CrateMover9000, 5),
+            None
+        );
+    }
+
+    #[test]
+    fn drawing_errors() {
+        assert_eq!(
+            parse_stacks("    [A]\n[B] x\n 1   2"),
+            Err("line 2, column 5: unexpected 'x'".to_string())
+        );
+        assert_eq!(
+            parse_stacks("[A]\n    [B\n 1   2"),
+            Err("line 2, column 5: malformed crate".to_string())
+        );
+        assert_eq!(
+            parse_stacks("        [C]\n 1   2"),
+            Err("line 1, column 9: crate [C] is not above a stack label".to_string())
+        );
+        assert_eq!(
+            parse_stacks("    [A]\n[B]\n 1   2"),
+            Err("line 1, column 5: crate [A] is not resting on stack '2'".to_string())
+        );
+        assert_eq!(
+            parse_moves("move 1 from 1 to 3", &["1".to_string()], 7),
+            Err("line 7: unknown stack '3'".to_string())
+        );
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -43,38 +311,202 @@ struct Move {
 fn parse_input(path: &str) -> (Vec<Vec<char>>, Vec<Move>) {
     let input_string = std::fs::read_to_string(path).unwrap();
     let mut parts = input_string.split("\n\n");
-    let stacks = parse_stacks(parts.next().unwrap());
-    let moves = parse_moves(parts.next().unwrap());
-    (stacks, moves)
+    let drawing = parse_stacks(parts.next().unwrap()).unwrap_or_else(|e| panic!("{}", e));
+    let first_move_line = drawing.height + 3; // crate rows, label row and blank line
+    let moves = parse_moves(parts.next().unwrap(), &drawing.labels, first_move_line)
+        .unwrap_or_else(|e| panic!("{}", e));
+    (drawing.stacks, moves)
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Drawing {
+    labels: Vec<String>,
+    stacks: Vec<Vec<char>>,
+    height: usize, // number of crate rows above the label row
 }
 
-fn parse_stacks(input: &str) -> Vec<Vec<char>> {
-    let mut stacks = vec![];
-    let width = input.find('\n').unwrap() + 1;
-    let height = ((input.len() + 1) / width) - 1; // exclude index row
-    let number_of_stacks = width / 4;
-    for i in 0..number_of_stacks {
-        let mut stack = vec![];
-        for j in 0..height {
-            let index = ((height - 1 - j) * width + 1) + 4 * i;
-            let item = input.chars().nth(index).unwrap();
-            if item != ' ' {
-                stack.push(item);
+// Labels and the columns they occupy, split on whitespace
+fn parse_labels(line: &str) -> Vec<(usize, usize, String)> {
+    let mut labels = vec![];
+    let mut current: Option<(usize, String)> = None;
+    for (column, c) in line.chars().chain(std::iter::once(' ')).enumerate() {
+        match (c.is_whitespace(), current.take()) {
+            (true, Some((start, label))) => labels.push((start, column, label)),
+            (true, None) => {}
+            (false, Some((start, mut label))) => {
+                label.push(c);
+                current = Some((start, label));
             }
+            (false, None) => current = Some((column, c.to_string())),
         }
-        stacks.push(stack);
     }
-    stacks
+    labels
+}
+
+// Reads the drawing column-wise: every crate belongs to the label below it
+fn parse_stacks(input: &str) -> Result<Drawing, String> {
+    let lines: Vec<&str> = input.lines().collect();
+    let label_line = lines.len();
+    let labels = match lines.last() {
+        Some(line) => parse_labels(line),
+        None => return Err("empty drawing".to_string()),
+    };
+    if labels.is_empty() {
+        return Err(format!("line {}: no stack labels", label_line));
+    }
+    for (i, (start, _, label)) in labels.iter().enumerate() {
+        if labels[..i].iter().any(|(_, _, other)| other == label) {
+            return Err(format!(
+                "line {}, column {}: duplicate stack label '{}'",
+                label_line,
+                start + 1,
+                label
+            ));
+        }
+    }
+
+    let mut stacks = vec![vec![]; labels.len()];
+    let height = lines.len() - 1;
+    for (row, line) in lines[..height].iter().enumerate().rev() {
+        let line_number = row + 1;
+        let level = height - 1 - row; // 0 is the floor
+        let mut chars = line.chars().enumerate();
+        while let Some((column, c)) = chars.next() {
+            if c == ' ' {
+                continue;
+            }
+            if c != '[' {
+                return Err(format!(
+                    "line {}, column {}: unexpected '{}'",
+                    line_number,
+                    column + 1,
+                    c
+                ));
+            }
+            let item = match (chars.next(), chars.next()) {
+                (Some((_, item)), Some((_, ']'))) if item != ' ' && item != ']' => item,
+                _ => {
+                    return Err(format!(
+                        "line {}, column {}: malformed crate",
+                        line_number,
+                        column + 1
+                    ))
+                }
+            };
+
+            let index = labels
+                .iter()
+                .position(|(start, end, _)| *start < column + 3 && column < *end)
+                .ok_or_else(|| {
+                    format!(
+                        "line {}, column {}: crate [{}] is not above a stack label",
+                        line_number,
+                        column + 1,
+                        item
+                    )
+                })?;
+            let stack = &mut stacks[index];
+            if stack.len() != level {
+                return Err(format!(
+                    "line {}, column {}: crate [{}] is not resting on stack '{}'",
+                    line_number,
+                    column + 1,
+                    item,
+                    labels[index].2
+                ));
+            }
+            stack.push(item);
+        }
+    }
+
+    Ok(Drawing {
+        labels: labels.into_iter().map(|(_, _, label)| label).collect(),
+        stacks,
+        height,
+    })
+}
+
+// Draws the stacks in the puzzle format, including the label row
+fn render_stacks(stacks: &[Vec<char>], labels: &[String]) -> String {
+    let widths: Vec<usize> = labels.iter().map(|label| label.len().max(3)).collect();
+    let height = stacks.iter().map(|stack| stack.len()).max().unwrap_or(0);
+    let mut lines = vec![];
+    for level in (0..height).rev() {
+        let cells = stacks.iter().zip(&widths).map(|(stack, width)| {
+            let cell = match stack.get(level) {
+                Some(item) => format!("[{}]", item),
+                None => String::new(),
+            };
+            format!("{:^width$}", cell, width = width)
+        });
+        lines.push(cells.collect::<Vec<String>>().join(" "));
+    }
+    let cells = labels
+        .iter()
+        .zip(&widths)
+        .map(|(label, width)| format!("{:^width$}", label, width = width));
+    lines.push(cells.collect::<Vec<String>>().join(" "));
+    lines.join("\n")
+}
+
+fn numbered_labels(number_of_stacks: usize) -> Vec<String> {
+    (1..=number_of_stacks).map(|i| i.to_string()).collect()
+}
+
+// Yields the drawing after every move
+struct Frames<'a> {
+    stacks: Vec<Vec<char>>,
+    moves: std::slice::Iter<'a, Move>,
+    crane: &'a mut dyn Crane,
+    labels: Vec<String>,
+}
+
+impl<'a> Frames<'a> {
+    fn new(stacks: Vec<Vec<char>>, moves: &'a [Move], crane: &'a mut dyn Crane) -> Self {
+        let labels = numbered_labels(stacks.len());
+        Frames {
+            stacks,
+            moves: moves.iter(),
+            crane,
+            labels,
+        }
+    }
+}
+
+impl Iterator for Frames<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let mov = self.moves.next()?;
+        self.crane.apply(&mut self.stacks, mov);
+        Some(render_stacks(&self.stacks, &self.labels))
+    }
 }
 
-fn parse_moves(input: &str) -> Vec<Move> {
+fn parse_moves(input: &str, labels: &[String], first_line: usize) -> Result<Vec<Move>, String> {
+    // Stacks are numbered by their position in the drawing, whatever their label
+    let stack_number = |label: &str, line: usize| {
+        labels
+            .iter()
+            .position(|x| x == label)
+            .map(|index| index + 1)
+            .ok_or_else(|| format!("line {}: unknown stack '{}'", line, label))
+    };
     input
         .lines()
-        .into_iter()
-        .map(|line| Move {
-            number: line.split(' ').nth(1).unwrap().parse::<usize>().unwrap(),
-            from: line.split(' ').nth(3).unwrap().parse::<usize>().unwrap(),
-            to: line.split(' ').nth(5).unwrap().parse::<usize>().unwrap(),
+        .enumerate()
+        .map(|(i, line)| {
+            let line_number = first_line + i;
+            match line.split(' ').collect::<Vec<&str>>().as_slice() {
+                ["move", number, "from", from, "to", to] => Ok(Move {
+                    number: number.parse().map_err(|_| {
+                        format!("line {}: invalid number '{}'", line_number, number)
+                    })?,
+                    from: stack_number(from, line_number)?,
+                    to: stack_number(to, line_number)?,
+                }),
+                _ => Err(format!("line {}: invalid move '{}'", line_number, line)),
+            }
         })
         .collect()
 }
@@ -83,43 +515,642 @@ fn get_top_crates(stacks: Vec<Vec<char>>) -> String {
     stacks.into_iter().map(|s| *s.last().unwrap()).collect()
 }
 
-fn arrange_with_silly_crate_mover_9000(
-    mut stacks: Vec<Vec<char>>,
-    moves: Vec<Move>,
-) -> Vec<Vec<char>> {
-    for mov in moves {
-        for _ in 0..mov.number {
-            let item = stacks.get_mut(mov.from - 1).unwrap().pop().unwrap();
-            stacks.get_mut(mov.to - 1).unwrap().push(item);
+trait Crane {
+    fn apply(&mut self, stacks: &mut [Vec<char>], mov: &Move);
+}
+
+// The source and target stack of a move, or None when they are the same stack
+fn stack_pair<'a>(
+    stacks: &'a mut [Vec<char>],
+    mov: &Move,
+) -> Option<(&'a mut Vec<char>, &'a mut Vec<char>)> {
+    let (from, to) = (mov.from - 1, mov.to - 1);
+    if from < to {
+        let (low, high) = stacks.split_at_mut(to);
+        Some((&mut low[from], &mut high[0]))
+    } else if from > to {
+        let (low, high) = stacks.split_at_mut(from);
+        Some((&mut high[0], &mut low[to]))
+    } else {
+        None
+    }
+}
+
+// Moves one crate at a time, which reverses them. Moving onto the same stack changes nothing.
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn apply(&mut self, stacks: &mut [Vec<char>], mov: &Move) {
+        if let Some((from, to)) = stack_pair(stacks, mov) {
+            let start = from.len() - mov.number;
+            to.extend(from.drain(start..).rev());
         }
     }
-    stacks
 }
 
-fn arrange_with_crate_mover_9001(mut stacks: Vec<Vec<char>>, moves: Vec<Move>) -> Vec<Vec<char>> {
-    let mut buffer = vec![];
-    for mov in moves {
-        for _ in 0..mov.number {
-            let item = stacks.get_mut(mov.from - 1).unwrap().pop().unwrap();
-            buffer.push(item);
+// Moves all crates at once, keeping their order
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn apply(&mut self, stacks: &mut [Vec<char>], mov: &Move) {
+        if let Some((from, to)) = stack_pair(stacks, mov) {
+            let start = from.len() - mov.number;
+            to.extend(from.drain(start..));
         }
-        for _ in 0..mov.number {
-            let item = buffer.pop().unwrap();
-            stacks.get_mut(mov.to - 1).unwrap().push(item);
+    }
+}
+
+// A 9001 that can only lift a limited number of crates, so large moves are split up
+struct CapacityLimitedCrane {
+    capacity: usize,
+}
+
+impl Crane for CapacityLimitedCrane {
+    fn apply(&mut self, stacks: &mut [Vec<char>], mov: &Move) {
+        assert!(self.capacity > 0, "crane capacity must be at least 1");
+        let mut remaining = mov.number;
+        while remaining > 0 {
+            let number = remaining.min(self.capacity);
+            let part = Move { number, ..*mov };
+            CrateMover9001.apply(stacks, &part);
+            remaining -= number;
         }
     }
+}
+
+// Lifts all crates at once and swaps every pair on the way, counted from the bottom of
+// the lifted crates. Every other crate is reversed with its neighbour, so ABCDE lands as
+// BADCE, with a leftover top crate staying in place. Moving onto the same stack changes
+// nothing.
+struct PairFlippingCrane;
+
+impl Crane for PairFlippingCrane {
+    fn apply(&mut self, stacks: &mut [Vec<char>], mov: &Move) {
+        if let Some((from, to)) = stack_pair(stacks, mov) {
+            let lifted = from.split_off(from.len() - mov.number);
+            for pair in lifted.chunks(2) {
+                to.extend(pair.iter().rev());
+            }
+        }
+    }
+}
+
+// Two machines that take turns executing moves
+struct DualCrane<A: Crane, B: Crane> {
+    first: A,
+    second: B,
+    second_is_next: bool,
+}
+
+impl<A: Crane, B: Crane> DualCrane<A, B> {
+    fn new(first: A, second: B) -> Self {
+        DualCrane {
+            first,
+            second,
+            second_is_next: false,
+        }
+    }
+}
+
+impl<A: Crane, B: Crane> Crane for DualCrane<A, B> {
+    fn apply(&mut self, stacks: &mut [Vec<char>], mov: &Move) {
+        if self.second_is_next {
+            self.second.apply(stacks, mov);
+        } else {
+            self.first.apply(stacks, mov);
+        }
+        self.second_is_next = !self.second_is_next;
+    }
+}
+
+fn arrange(mut stacks: Vec<Vec<char>>, moves: &[Move], crane: &mut dyn Crane) -> Vec<Vec<char>> {
+    for mov in moves {
+        crane.apply(&mut stacks, mov);
+    }
     stacks
 }
 
+fn arrange_with_silly_crate_mover_9000(stacks: Vec<Vec<char>>, moves: Vec<Move>) -> Vec<Vec<char>> {
+    arrange(stacks, &moves, &mut CrateMover9000)
+}
+
+fn arrange_with_crate_mover_9001(stacks: Vec<Vec<char>>, moves: Vec<Move>) -> Vec<Vec<char>> {
+    arrange(stacks, &moves, &mut CrateMover9001)
+}
+
+// Crates taken from the source stack and placed on the target stack, both bottom to top
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct JournalEntry {
+    mov: Move,
+    lifted: Vec<char>,
+    placed: Vec<char>,
+}
+
+// Applies validated moves and keeps a journal, so they can be undone and redone
+struct Executor {
+    stacks: Vec<Vec<char>>,
+    journal: Vec<JournalEntry>,
+    applied: usize,
+}
+
+impl Executor {
+    fn new(stacks: Vec<Vec<char>>) -> Self {
+        Executor {
+            stacks,
+            journal: vec![],
+            applied: 0,
+        }
+    }
+
+    fn validate(&self, mov: &Move) -> Result<(), String> {
+        let move_number = self.applied + 1;
+        for stack in [mov.from, mov.to] {
+            if stack == 0 || stack > self.stacks.len() {
+                return Err(format!(
+                    "move {}: there is no stack {} ({:?})",
+                    move_number, stack, mov
+                ));
+            }
+        }
+        let available = self.stacks[mov.from - 1].len();
+        if available < mov.number {
+            return Err(format!(
+                "move {}: cannot take {} crates from stack {} holding {} ({:?})",
+                move_number, mov.number, mov.from, available, mov
+            ));
+        }
+        Ok(())
+    }
+
+    // Applying a move discards anything that could still be redone
+    fn apply(&mut self, mov: &Move, crane: &mut dyn Crane) -> Result<(), String> {
+        self.validate(mov)?;
+        let from = &self.stacks[mov.from - 1];
+        let lifted = from[from.len() - mov.number..].to_vec();
+        crane.apply(&mut self.stacks, mov);
+        let to = &self.stacks[mov.to - 1];
+        let placed = to[to.len() - mov.number..].to_vec();
+
+        self.journal.truncate(self.applied);
+        self.journal.push(JournalEntry {
+            mov: mov.clone(),
+            lifted,
+            placed,
+        });
+        self.applied += 1;
+        Ok(())
+    }
+
+    fn run(&mut self, moves: &[Move], crane: &mut dyn Crane) -> Result<(), String> {
+        for mov in moves {
+            self.apply(mov, crane)?;
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self) -> bool {
+        if self.applied == 0 {
+            return false;
+        }
+        self.applied -= 1;
+        let entry = &self.journal[self.applied];
+        let to = &mut self.stacks[entry.mov.to - 1];
+        to.truncate(to.len() - entry.mov.number);
+        self.stacks[entry.mov.from - 1].extend(&entry.lifted);
+        true
+    }
+
+    fn redo(&mut self) -> bool {
+        if self.applied == self.journal.len() {
+            return false;
+        }
+        let entry = &self.journal[self.applied];
+        let from = &mut self.stacks[entry.mov.from - 1];
+        from.truncate(from.len() - entry.mov.number);
+        self.stacks[entry.mov.to - 1].extend(&entry.placed);
+        self.applied += 1;
+        true
+    }
+
+    // Undoes every applied move, recovering the initial stacks
+    fn rewind(&mut self) {
+        while self.undo() {}
+    }
+}
+
+// Lower bound on the remaining moves: every move takes crates off one stack and puts
+// them on one other stack
+fn moves_needed(stacks: &[Vec<char>], target: &[Vec<char>]) -> usize {
+    let mut must_give = 0;
+    let mut must_receive = 0;
+    for (stack, goal) in stacks.iter().zip(target) {
+        let correct = stack.iter().zip(goal).take_while(|(a, b)| a == b).count();
+        if stack.len() > correct {
+            must_give += 1;
+        }
+        if goal.len() > correct {
+            must_receive += 1;
+        }
+    }
+    must_give.max(must_receive)
+}
+
+fn search(
+    stacks: &mut Vec<Vec<char>>,
+    target: &[Vec<char>],
+    crane: &mut dyn Crane,
+    path: &mut Vec<Move>,
+    bound: usize,
+) -> bool {
+    let estimate = moves_needed(stacks, target);
+    if estimate == 0 {
+        return true;
+    }
+    if path.len() + estimate > bound {
+        return false;
+    }
+    for from in 1..=stacks.len() {
+        for to in 1..=stacks.len() {
+            if from == to {
+                continue;
+            }
+            for number in 1..=stacks[from - 1].len() {
+                let mov = Move { number, from, to };
+                // Never take back the previous move right away
+                if path.last().is_some_and(|previous| {
+                    previous.from == to && previous.to == from && previous.number == number
+                }) {
+                    continue;
+                }
+
+                let source = &stacks[from - 1];
+                let lifted = source[source.len() - number..].to_vec();
+                crane.apply(stacks, &mov);
+                path.push(mov);
+                if search(stacks, target, crane, path, bound) {
+                    return true;
+                }
+                path.pop();
+                let destination = &mut stacks[to - 1];
+                destination.truncate(destination.len() - number);
+                stacks[from - 1].extend(lifted);
+            }
+        }
+    }
+    false
+}
+
+// Shortest sequence of moves from one arrangement to another using IDA*. The crane
+// must not keep state between moves, like the CrateMover 9000 and 9001.
+fn plan_moves(
+    stacks: &[Vec<char>],
+    target: &[Vec<char>],
+    crane: &mut dyn Crane,
+    max_moves: usize,
+) -> Option<Vec<Move>> {
+    let crates = |stacks: &[Vec<char>]| -> Vec<char> {
+        let mut crates: Vec<char> = stacks.iter().flatten().copied().collect();
+        crates.sort();
+        crates
+    };
+    if stacks.len() != target.len() || crates(stacks) != crates(target) {
+        return None;
+    }
+
+    let mut stacks = stacks.to_vec();
+    let mut path = vec![];
+    for bound in moves_needed(&stacks, target)..=max_moves {
+        if search(&mut stacks, target, crane, &mut path, bound) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+const NIL: u32 = u32::MAX;
+
+struct RopeNode {
+    item: char,
+    priority: u32,
+    size: u32,
+    reversed: bool, // children still have to be swapped
+    left: u32,
+    right: u32,
+}
+
+// Stacks as implicit treaps sharing one arena. Splitting off the top crates, reversing
+// them and putting them on another stack are all O(log n), whatever the move size.
+struct Ropes {
+    nodes: Vec<RopeNode>,
+    roots: Vec<u32>,
+    seed: u32,
+}
+
+impl Ropes {
+    fn new(stacks: &[Vec<char>]) -> Self {
+        let mut ropes = Ropes {
+            nodes: Vec::with_capacity(stacks.iter().map(|stack| stack.len()).sum()),
+            roots: vec![],
+            seed: 0x2545F491,
+        };
+        for stack in stacks {
+            let mut root = NIL;
+            for item in stack {
+                let node = ropes.new_node(*item);
+                root = ropes.merge(root, node);
+            }
+            ropes.roots.push(root);
+        }
+        ropes
+    }
+
+    fn new_node(&mut self, item: char) -> u32 {
+        // xorshift32
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.nodes.push(RopeNode {
+            item,
+            priority: self.seed,
+            size: 1,
+            reversed: false,
+            left: NIL,
+            right: NIL,
+        });
+        (self.nodes.len() - 1) as u32
+    }
+
+    fn size(&self, node: u32) -> u32 {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node as usize].size
+        }
+    }
+
+    fn reverse(&mut self, node: u32) {
+        if node != NIL {
+            let node = &mut self.nodes[node as usize];
+            node.reversed = !node.reversed;
+        }
+    }
+
+    fn push_down(&mut self, node: u32) {
+        let n = &mut self.nodes[node as usize];
+        if n.reversed {
+            n.reversed = false;
+            std::mem::swap(&mut n.left, &mut n.right);
+            let (left, right) = (n.left, n.right);
+            self.reverse(left);
+            self.reverse(right);
+        }
+    }
+
+    fn update(&mut self, node: u32) {
+        let (left, right) = (
+            self.nodes[node as usize].left,
+            self.nodes[node as usize].right,
+        );
+        self.nodes[node as usize].size = self.size(left) + self.size(right) + 1;
+    }
+
+    fn merge(&mut self, a: u32, b: u32) -> u32 {
+        if a == NIL {
+            return b;
+        }
+        if b == NIL {
+            return a;
+        }
+        if self.nodes[a as usize].priority > self.nodes[b as usize].priority {
+            self.push_down(a);
+            let right = self.merge(self.nodes[a as usize].right, b);
+            self.nodes[a as usize].right = right;
+            self.update(a);
+            a
+        } else {
+            self.push_down(b);
+            let left = self.merge(a, self.nodes[b as usize].left);
+            self.nodes[b as usize].left = left;
+            self.update(b);
+            b
+        }
+    }
+
+    // The first `count` crates (from the bottom) and the rest
+    fn split(&mut self, node: u32, count: u32) -> (u32, u32) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+        self.push_down(node);
+        let left = self.nodes[node as usize].left;
+        let right = self.nodes[node as usize].right;
+        if self.size(left) < count {
+            let (a, b) = self.split(right, count - self.size(left) - 1);
+            self.nodes[node as usize].right = a;
+            self.update(node);
+            (node, b)
+        } else {
+            let (a, b) = self.split(left, count);
+            self.nodes[node as usize].left = b;
+            self.update(node);
+            (a, node)
+        }
+    }
+
+    fn apply(&mut self, mov: &Move, reverse: bool) {
+        let (from, to) = (mov.from - 1, mov.to - 1);
+        // Like the cranes, putting crates back where they came from changes nothing
+        if from == to {
+            return;
+        }
+        let size = self.size(self.roots[from]);
+        assert!(mov.number as u32 <= size, "not enough crates for {:?}", mov);
+        let (rest, lifted) = self.split(self.roots[from], size - mov.number as u32);
+        if reverse {
+            self.reverse(lifted);
+        }
+        self.roots[from] = rest;
+        self.roots[to] = self.merge(self.roots[to], lifted);
+    }
+
+    fn collect(&mut self, node: u32, items: &mut Vec<char>) {
+        if node == NIL {
+            return;
+        }
+        self.push_down(node);
+        self.collect(self.nodes[node as usize].left, items);
+        items.push(self.nodes[node as usize].item);
+        self.collect(self.nodes[node as usize].right, items);
+    }
+
+    fn stacks(&mut self) -> Vec<Vec<char>> {
+        (0..self.roots.len())
+            .map(|i| {
+                let mut items = vec![];
+                self.collect(self.roots[i], &mut items);
+                items
+            })
+            .collect()
+    }
+}
+
+// Bulk arrangement for huge inputs; `reverse` selects the CrateMover 9000 behaviour
+fn arrange_with_ropes(stacks: &[Vec<char>], moves: &[Move], reverse: bool) -> Vec<Vec<char>> {
+    let mut ropes = Ropes::new(stacks);
+    for mov in moves {
+        ropes.apply(mov, reverse);
+    }
+    ropes.stacks()
+}
+
+// Random stacks and valid moves, with lots of crates per move
+fn generate_input(
+    number_of_stacks: usize,
+    crates_per_stack: usize,
+    number_of_moves: usize,
+) -> (Vec<Vec<char>>, Vec<Move>) {
+    let mut seed: u64 = 0x9E3779B97F4A7C15;
+    let mut random = |n: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % n as u64) as usize
+    };
+    let stacks: Vec<Vec<char>> = (0..number_of_stacks)
+        .map(|_| {
+            (0..crates_per_stack)
+                .map(|_| (b'A' + random(26) as u8) as char)
+                .collect()
+        })
+        .collect();
+    let mut sizes: Vec<usize> = stacks.iter().map(|stack| stack.len()).collect();
+    let mut moves = Vec::with_capacity(number_of_moves);
+    while moves.len() < number_of_moves {
+        let from = random(number_of_stacks);
+        let to = random(number_of_stacks);
+        if sizes[from] == 0 {
+            continue;
+        }
+        let number = random(sizes[from]) + 1;
+        sizes[from] -= number;
+        sizes[to] += number;
+        moves.push(Move {
+            number,
+            from: from + 1,
+            to: to + 1,
+        });
+    }
+    (stacks, moves)
+}
+
+fn benchmark(crates_per_stack: usize, number_of_moves: usize) {
+    let (stacks, moves) = generate_input(9, crates_per_stack, number_of_moves);
+    let crates: usize = moves.iter().map(|mov| mov.number).sum();
+    println!(
+        "{} moves moving {} crates between 9 stacks of {} crates",
+        number_of_moves, crates, crates_per_stack
+    );
+    for (name, reverse) in [("CrateMover 9000", true), ("CrateMover 9001", false)] {
+        let start = std::time::Instant::now();
+        let arranged = arrange_with_ropes(&stacks, &moves, reverse);
+        println!(
+            "{} with ropes: {} in {:?}",
+            name,
+            get_top_crates(arranged),
+            start.elapsed()
+        );
+    }
+}
+
+fn animate(stacks: Vec<Vec<char>>, moves: &[Move], crane: &mut dyn Crane) {
+    println!(
+        "{}\n",
+        render_stacks(&stacks, &numbered_labels(stacks.len()))
+    );
+    for (mov, frame) in moves.iter().zip(Frames::new(stacks, moves, crane)) {
+        println!(
+            "move {} from {} to {}\n{}\n",
+            mov.number, mov.from, mov.to, frame
+        );
+    }
+}
+
 fn main() {
     println!("Hello, day 5!");
 
+    let args: Vec<String> = std::env::args().collect();
+    // `cargo run --release --bin 05 -- bench [crates per stack] [moves]` times bulk moves
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let crates_per_stack = args.get(2).map_or(1_000_000, |x| x.parse().unwrap());
+        let number_of_moves = args.get(3).map_or(1_000_000, |x| x.parse().unwrap());
+        benchmark(crates_per_stack, number_of_moves);
+        return;
+    }
+    // `cargo run --bin 05 -- animate 9000` steps through a crane run instead
+    if args.get(1).map(String::as_str) == Some("animate") {
+        let (stacks, moves) = parse_input("./input/05/input.txt");
+        match args.get(2).map(String::as_str) {
+            Some("9000") => animate(stacks, &moves, &mut CrateMover9000),
+            Some("9001") => animate(stacks, &moves, &mut CrateMover9001),
+            _ => println!("usage: animate <9000|9001>"),
+        }
+        return;
+    }
+
     let (stacks, moves) = parse_input("./input/05/input.txt");
     let arranged = arrange_with_silly_crate_mover_9000(stacks.clone(), moves.clone());
     let top_crates = get_top_crates(arranged);
     println!("Part 1: {}", top_crates);
 
-    let arranged = arrange_with_crate_mover_9001(stacks, moves);
+    let arranged = arrange_with_crate_mover_9001(stacks.clone(), moves.clone());
     let top_crates = get_top_crates(arranged);
     println!("Part 2: {}", top_crates);
+
+    let cranes: Vec<(&str, Box<dyn Crane>)> = vec![
+        (
+            "capacity limited crane",
+            Box::new(CapacityLimitedCrane { capacity: 3 }),
+        ),
+        ("pair flipping crane", Box::new(PairFlippingCrane)),
+        (
+            "dual crane",
+            Box::new(DualCrane::new(CrateMover9000, CrateMover9001)),
+        ),
+    ];
+    for (name, mut crane) in cranes {
+        let arranged = arrange(stacks.clone(), &moves, crane.as_mut());
+        println!("With {}: {}", name, get_top_crates(arranged));
+    }
+
+    for (name, crane) in [
+        ("CrateMover 9000", &mut CrateMover9000 as &mut dyn Crane),
+        ("CrateMover 9001", &mut CrateMover9001),
+    ] {
+        let number_of_moves = 3;
+        let target = arrange(stacks.clone(), &moves[..number_of_moves], crane);
+        match plan_moves(&stacks, &target, crane, number_of_moves) {
+            Some(plan) => println!(
+                "{} needs {} moves for the first {} moves of the input",
+                name,
+                plan.len(),
+                number_of_moves
+            ),
+            None => println!("{} found no plan", name),
+        }
+    }
+
+    let mut executor = Executor::new(stacks.clone());
+    match executor.run(&moves, &mut CrateMover9001) {
+        Ok(()) => {
+            executor.rewind();
+            println!(
+                "Journal of {} moves rewinds to the initial stacks: {}",
+                executor.journal.len(),
+                executor.stacks == stacks
+            );
+            while executor.redo() {}
+            println!("Replayed journal: {}", get_top_crates(executor.stacks));
+        }
+        Err(e) => println!("Invalid moves: {}", e),
+    }
 }
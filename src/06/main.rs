@@ -1,4 +1,5 @@
-use itertools::Itertools;
+use std::collections::VecDeque;
+use std::io::{self, BufReader, Bytes, ErrorKind, Read};
 
 #[cfg(test)]
 mod tests {
@@ -30,16 +31,297 @@ mod tests {
             26
         );
     }
+
+    #[test]
+    fn test_streaming() {
+        let file = std::fs::File::open("./input/06/input.txt").unwrap();
+        let input = std::fs::read_to_string("./input/06/input.txt").unwrap();
+        assert_eq!(
+            find_first_marker_in(file, 14).unwrap(),
+            Some(find_first_marker(&input, 14))
+        );
+
+        // Long windows and a marker right at the end of a long stream
+        let mut stream = vec![b'a'; 1_000_000];
+        stream.extend(0..=255);
+        assert_eq!(
+            find_first_marker_in(&stream[..], 256).unwrap(),
+            Some(stream.len())
+        );
+        assert_eq!(find_first_marker_in(&stream[..], 257).unwrap(), None);
+        assert_eq!(find_first_marker_in(&b"aaa"[..], 1).unwrap(), Some(1));
+    }
+
+    // Hands out one byte per read and fails every other read
+    struct FlakyReader {
+        data: Vec<u8>,
+        position: usize,
+        calls: usize,
+        error: ErrorKind,
+    }
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(2) {
+                return Err(io::Error::from(self.error));
+            }
+            match self.data.get(self.position) {
+                Some(byte) => {
+                    buffer[0] = *byte;
+                    self.position += 1;
+                    Ok(1)
+                }
+                None => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let reader = |error| FlakyReader {
+            data: b"mjqjpqmgbljsphdztnvjfqwrcgsmlb".to_vec(),
+            position: 0,
+            calls: 0,
+            error,
+        };
+        let found = find_first_marker_in(reader(ErrorKind::Interrupted), 4);
+        assert_eq!(found.unwrap(), Some(7));
+        let found = find_first_marker_in(reader(ErrorKind::BrokenPipe), 4);
+        assert_eq!(found.unwrap_err().kind(), ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_decoder() {
+        let events: Vec<Event> = Decoder::new(&b"abcdaaaaefgh"[..])
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::StartOfPacket { position: 4 },
+                Event::Payload {
+                    start: 4,
+                    data: b"aaa".to_vec()
+                },
+                Event::StartOfPacket { position: 11 },
+                Event::Payload {
+                    start: 11,
+                    data: b"h".to_vec()
+                },
+            ]
+        );
+
+        let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+        let mut decoder = Decoder::new(input.as_bytes()).map(Result::unwrap);
+        assert_eq!(
+            decoder.next(),
+            Some(Event::Payload {
+                start: 0,
+                data: b"mjq".to_vec()
+            })
+        );
+        assert_eq!(decoder.next(), Some(Event::StartOfPacket { position: 7 }));
+        let first_message = decoder.find(|event| matches!(event, Event::StartOfMessage { .. }));
+        assert_eq!(first_message, Some(Event::StartOfMessage { position: 19 }));
+    }
+
+    #[test]
+    fn test_decoder_read_errors() {
+        let reader = |error| FlakyReader {
+            data: b"mjqjpqmgbljsphdztnvjfqwrcgsmlb".to_vec(),
+            position: 0,
+            calls: 0,
+            error,
+        };
+        let events: Vec<Event> = Decoder::new(reader(ErrorKind::Interrupted))
+            .collect::<io::Result<_>>()
+            .unwrap();
+        let expected: Vec<Event> = Decoder::new(&b"mjqjpqmgbljsphdztnvjfqwrcgsmlb"[..])
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(events, expected);
+
+        let mut decoder = Decoder::new(reader(ErrorKind::BrokenPipe));
+        let error = decoder.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::BrokenPipe);
+        assert!(decoder.next().is_none());
+    }
+}
+
+// Sliding window over a byte stream that keeps track of how many distinct bytes it holds
+struct MarkerDetector {
+    window: Vec<u8>, // ring buffer with the most recent bytes
+    counts: [usize; 256],
+    distinct: usize,
+    position: usize, // number of bytes seen so far
+}
+
+impl MarkerDetector {
+    fn new(length: usize) -> Self {
+        assert!(length > 0, "markers need at least one character");
+        MarkerDetector {
+            window: vec![0; length],
+            counts: [0; 256],
+            distinct: 0,
+            position: 0,
+        }
+    }
+
+    // Returns true when the last `length` bytes are all different
+    fn push(&mut self, byte: u8) -> bool {
+        let length = self.window.len();
+        let slot = self.position % length;
+        if self.position >= length {
+            let old = self.window[slot] as usize;
+            self.counts[old] -= 1;
+            if self.counts[old] == 0 {
+                self.distinct -= 1;
+            }
+        }
+        self.window[slot] = byte;
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 1 {
+            self.distinct += 1;
+        }
+        self.position += 1;
+        self.distinct == length
+    }
+
+    // Forget the window, so the next marker cannot overlap the previous one
+    fn reset(&mut self) {
+        self.counts = [0; 256];
+        self.distinct = 0;
+        self.position = 0;
+    }
+}
+
+// Scans the stream in O(n) with constant memory
+fn find_first_marker_in(
+    mut reader: impl Read,
+    distinct_characters: usize,
+) -> io::Result<Option<usize>> {
+    let mut detector = MarkerDetector::new(distinct_characters);
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(None),
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        for byte in &buffer[..read] {
+            if detector.push(*byte) {
+                return Ok(Some(detector.position));
+            }
+        }
+    }
 }
 
 fn find_first_marker(input: &str, distinct_characters: usize) -> usize {
-    let input = input.to_string().chars().collect::<Vec<char>>();
-    for (index, window) in input.windows(distinct_characters).enumerate() {
-        if window.iter().unique().count() == distinct_characters {
-            return index + distinct_characters;
+    find_first_marker_in(input.as_bytes(), distinct_characters)
+        .unwrap()
+        .expect("no marker found")
+}
+
+const START_OF_PACKET_LENGTH: usize = 4;
+const START_OF_MESSAGE_LENGTH: usize = 14;
+
+#[derive(Debug, Eq, PartialEq)]
+enum Event {
+    // Positions are the number of bytes up to and including the marker
+    StartOfPacket { position: usize },
+    StartOfMessage { position: usize },
+    // Data between markers, without the marker bytes themselves
+    Payload { start: usize, data: Vec<u8> },
+}
+
+// Walks the whole stream, detecting both kinds of markers in a single pass. Interrupted
+// reads are retried. Any other read error is yielded once, after which the decoder stops
+// and the bytes after the last event are dropped.
+struct Decoder<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    packet: MarkerDetector,
+    message: MarkerDetector,
+    position: usize,
+    payload: Vec<u8>,
+    payload_start: usize,
+    pending: VecDeque<Event>,
+    failed: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    fn new(reader: R) -> Self {
+        Decoder {
+            bytes: BufReader::new(reader).bytes(),
+            packet: MarkerDetector::new(START_OF_PACKET_LENGTH),
+            message: MarkerDetector::new(START_OF_MESSAGE_LENGTH),
+            position: 0,
+            payload: vec![],
+            payload_start: 0,
+            pending: VecDeque::new(),
+            failed: false,
+        }
+    }
+
+    fn flush_payload(&mut self, marker_length: usize) {
+        let end = self.payload.len().saturating_sub(marker_length);
+        if end > 0 {
+            let data = self.payload[..end].to_vec();
+            self.pending.push_back(Event::Payload {
+                start: self.payload_start,
+                data,
+            });
+        }
+        self.payload.clear();
+        self.payload_start = self.position;
+    }
+}
+
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        if self.failed {
+            return None;
+        }
+        while self.pending.is_empty() {
+            let byte = match self.bytes.next() {
+                Some(Ok(byte)) => byte,
+                Some(Err(e)) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+                None => {
+                    self.flush_payload(0);
+                    return self.pending.pop_front().map(Ok);
+                }
+            };
+            self.position += 1;
+            self.payload.push(byte);
+
+            let packet = self.packet.push(byte);
+            let message = self.message.push(byte);
+            if message {
+                self.flush_payload(START_OF_MESSAGE_LENGTH);
+            } else if packet {
+                self.flush_payload(START_OF_PACKET_LENGTH);
+            }
+            if packet {
+                self.packet.reset();
+                self.pending.push_back(Event::StartOfPacket {
+                    position: self.position,
+                });
+            }
+            if message {
+                self.message.reset();
+                self.pending.push_back(Event::StartOfMessage {
+                    position: self.position,
+                });
+            }
         }
+        self.pending.pop_front().map(Ok)
     }
-    panic!("no marker found");
 }
 
 fn main() {
@@ -49,6 +331,23 @@ fn main() {
     let marker_id = find_first_marker(&input, 4);
     println!("Part 1: {}", marker_id);
 
-    let marker_id = find_first_marker(&input, 14);
+    let file = std::fs::File::open("./input/06/input.txt").unwrap();
+    let marker_id = find_first_marker_in(file, 14)
+        .unwrap()
+        .expect("no marker found");
     println!("Part 2: {}", marker_id);
+
+    let file = std::fs::File::open("./input/06/input.txt").unwrap();
+    let (mut packets, mut messages, mut payload) = (0, 0, 0);
+    for event in Decoder::new(file) {
+        match event.unwrap() {
+            Event::StartOfPacket { .. } => packets += 1,
+            Event::StartOfMessage { .. } => messages += 1,
+            Event::Payload { data, .. } => payload += data.len(),
+        }
+    }
+    println!(
+        "Decoded {} packets and {} messages with {} payload bytes",
+        packets, messages, payload
+    );
 }
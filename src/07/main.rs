@@ -1,7 +1,11 @@
 use itertools::Itertools;
 // use petgraph::dot::{Config, Dot};
-use petgraph::graph::NodeIndex;
-use petgraph::{Direction, Graph};
+use petgraph::stable_graph::{NodeIndex, StableGraph};
+use petgraph::visit::NodeIndexable;
+use petgraph::Direction;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
 
 #[cfg(test)]
 mod tests {
@@ -9,27 +13,416 @@ mod tests {
 
     #[test]
     fn calculate_dir_sizes() {
-        let graph = parse_input("./src/07/test.txt");
-        let e = node_index(&graph, "e");
-        let a = node_index(&graph, "a");
-        let d = node_index(&graph, "d");
-        let root = node_index(&graph, "/");
-        assert_eq!(dir_size(&graph, e), 584);
-        assert_eq!(dir_size(&graph, a), 94853);
-        assert_eq!(dir_size(&graph, d), 24933642);
-        assert_eq!(dir_size(&graph, root), 48381165);
+        let fs = parse_input("./src/07/test.txt");
+        let sizes = DirSizes::new(&fs);
+        let e = fs.lookup("/a/e").unwrap();
+        let a = fs.lookup("/a").unwrap();
+        let d = fs.lookup("/d").unwrap();
+        assert_eq!(sizes.get(e), 584);
+        assert_eq!(sizes.get(a), 94853);
+        assert_eq!(sizes.get(d), 24933642);
+        assert_eq!(sizes.get(fs.root), 48381165);
     }
 
     #[test]
     fn test_part_one() {
-        let graph = parse_input("./src/07/test.txt");
-        assert_eq!(part_one(&graph), 95437);
+        let fs = parse_input("./src/07/test.txt");
+        assert_eq!(part_one(&DirSizes::new(&fs)), 95437);
     }
 
     #[test]
     fn test_part_two() {
-        let graph = parse_input("./src/07/test.txt");
-        assert_eq!(smallest_directory_to_delete(&graph), 24933642);
+        let fs = parse_input("./src/07/test.txt");
+        let sizes = DirSizes::new(&fs);
+        let dir = smallest_directory_to_delete(&fs, &sizes, DISK_SIZE, REQUIRED_FREE).unwrap();
+        assert_eq!(fs.path(dir), "/d");
+        assert_eq!(sizes.get(dir), 24933642);
+    }
+
+    #[test]
+    fn cleanup_plans() {
+        let fs = parse_input("./src/07/test.txt");
+        let sizes = DirSizes::new(&fs);
+        let plan = |disk_size, required_free, strategy| {
+            plan_cleanup(&fs, &sizes, disk_size, required_free, strategy)
+        };
+        let paths = |paths: &[&str]| paths.iter().map(|x| x.to_string()).collect();
+
+        assert_eq!(
+            plan(70000000, 30000000, Cleanup::SingleDirectory),
+            Some(CleanupPlan {
+                paths: paths(&["/d"]),
+                freed: 24933642
+            })
+        );
+        // Only 8381165 bytes have to go, which c.dat covers on its own
+        for strategy in [Cleanup::FewestBytes, Cleanup::FewestItems] {
+            assert_eq!(
+                plan(70000000, 30000000, strategy),
+                Some(CleanupPlan {
+                    paths: paths(&["/c.dat"]),
+                    freed: 8504156
+                })
+            );
+        }
+        assert_eq!(
+            plan(70000000, 33000000, Cleanup::FewestBytes),
+            Some(CleanupPlan {
+                paths: paths(&["/d/d.log", "/d/j"]),
+                freed: 8033020 + 4060174
+            })
+        );
+        // Both files in /a/e and /a go, so the directory is deleted as a whole
+        assert_eq!(
+            plan(48381165 - 94853, 0, Cleanup::FewestBytes),
+            Some(CleanupPlan {
+                paths: paths(&["/a"]),
+                freed: 94853
+            })
+        );
+        assert_eq!(
+            plan(48381165 - 30000000, 1, Cleanup::FewestItems),
+            Some(CleanupPlan {
+                paths: paths(&["/c.dat", "/d"]),
+                freed: 8504156 + 24933642
+            })
+        );
+        assert_eq!(
+            plan(70000000, 10000000, Cleanup::FewestBytes),
+            Some(CleanupPlan {
+                paths: vec![],
+                freed: 0
+            })
+        );
+        assert_eq!(plan(1000, 48381165, Cleanup::FewestItems), None);
+    }
+
+    #[test]
+    fn cleanup_plans_for_large_disks() {
+        let transcript = "$ cd /\n$ ls\ndir big\n5000000000 a\n7000000000 b\n$ cd big\n$ ls\n6000000000 c\n4500000000 d\n";
+        let fs = parse_transcript(transcript).unwrap();
+        let sizes = DirSizes::new(&fs);
+        let plan = |strategy| plan_cleanup(&fs, &sizes, 25000000000, 11900000000, strategy);
+        let paths = |paths: &[&str]| paths.iter().map(|x| x.to_string()).collect();
+
+        assert_eq!(
+            plan(Cleanup::FewestBytes),
+            Some(CleanupPlan {
+                paths: paths(&["/a", "/big/d"]),
+                freed: 9500000000
+            })
+        );
+        assert_eq!(
+            plan(Cleanup::FewestItems),
+            Some(CleanupPlan {
+                paths: paths(&["/big"]),
+                freed: 10500000000
+            })
+        );
+    }
+
+    #[test]
+    fn subset_sums() {
+        // Against every subset of a few awkward sizes
+        let sizes = [8, 3, 0, 5, 5, 9, 14, 2, 6, 11];
+        for min in 1..=63 {
+            let best = (0..1 << sizes.len())
+                .map(|mask: u32| {
+                    (0..sizes.len())
+                        .filter(|i| mask & (1 << i) != 0)
+                        .map(|i| sizes[i])
+                        .sum::<usize>()
+                })
+                .filter(|total| *total >= min)
+                .min()
+                .unwrap();
+            let picked = subset_sum_below(&sizes, min, 64).unwrap();
+            assert_eq!(picked.iter().map(|i| sizes[*i]).sum::<usize>(), best);
+            assert_eq!(subset_sum_below(&sizes, min, best), None);
+        }
+    }
+
+    #[test]
+    fn directories_with_the_same_name() {
+        let transcript = "$ cd /\n$ ls\ndir a\ndir e\n$ cd e\n$ ls\n10 x\n$ cd ..\n$ cd a\n$ ls\ndir e\n$ cd e\n$ ls\n20 y\n";
+        let fs = parse_transcript(transcript).unwrap();
+        let sizes = DirSizes::new(&fs);
+        let outer = fs.lookup("/e").unwrap();
+        let inner = fs.lookup("/a/e").unwrap();
+        assert_ne!(outer, inner);
+        assert_eq!(sizes.get(outer), 10);
+        assert_eq!(sizes.get(inner), 20);
+        assert_eq!(fs.path(inner), "/a/e");
+        assert_eq!(fs.parent(inner), fs.lookup("/a"));
+        assert_eq!(fs.parent(fs.root), None);
+        assert_eq!(
+            fs.lookup("/a/e/y").map(|x| fs.path(x)),
+            Some("/a/e/y".to_string())
+        );
+        assert_eq!(fs.lookup("/a/x"), None);
+    }
+
+    #[test]
+    fn list_children() {
+        let fs = parse_input("./src/07/test.txt");
+        let names: Vec<&str> = fs.children(fs.root).map(|x| fs.name(x)).sorted().collect();
+        assert_eq!(names, vec!["a", "b.txt", "c.dat", "d"]);
+        assert_eq!(fs.lookup("/"), Some(fs.root));
+        assert_eq!(fs.path(fs.root), "/");
+    }
+
+    #[test]
+    fn shell_commands() {
+        let transcript = [
+            "$ cd /",
+            "$ mkdir a",
+            "$ mkdir a/b",
+            "$ mkdir /a/b/c",
+            "$ cd a/b/c",
+            "$ pwd",
+            "/a/b/c",
+            "$ ls",
+            "100 x",
+            "dir y",
+            "$ ls",
+            "100 x",
+            "dir y",
+            "$ cd ../../..",
+            "$ cd ./a/../a/b",
+            "$ ls",
+            "5 z",
+            "$ cd /a/b/c/y",
+            "$ ls",
+            "7 w",
+            "$ mkdir /a/b",
+            "$ cd /",
+            "$ mkdir -p d/e/../f",
+            "$ mkdir -p /a/b/c/g/h",
+            "$ rm -r /a/b/c/y",
+            "$ rm -rf /a/b/c/y",
+            "$ rm /a/b/z",
+            "$ ls",
+            "dir a",
+        ]
+        .join("\n");
+        let fs = parse_transcript(&transcript).unwrap();
+        let sizes = DirSizes::new(&fs);
+        assert_eq!(sizes.get(fs.root), 100);
+        assert_eq!(sizes.get(fs.lookup("/a/b/c").unwrap()), 100);
+        assert_eq!(fs.lookup("/a/b/c/y"), None);
+        assert_eq!(fs.lookup("/a/b/z"), None);
+        assert_eq!(fs.children(fs.lookup("/a/b").unwrap()).count(), 1);
+        assert!(fs.lookup("/d/e").is_some());
+        assert!(fs.lookup("/d/f").is_some());
+        assert!(fs.lookup("/a/b/c/g/h").is_some());
+    }
+
+    #[test]
+    fn transcript_errors() {
+        let error = |lines: &[&str]| parse_transcript(&lines.join("\n")).err().unwrap();
+        assert_eq!(
+            error(&["$ cd /", "$ ls", "dir a", "$ cd b"]),
+            "line 4: cd: no such file or directory 'b' in /"
+        );
+        assert_eq!(
+            error(&["$ ls", "10 a", "$ cd a"]),
+            "line 3: cd: /a is not a directory"
+        );
+        assert_eq!(
+            error(&["$ cd /", "$ cat x"]),
+            "line 2: unknown command 'cat x'"
+        );
+        assert_eq!(
+            error(&["$ cd /", "10 a"]),
+            "line 2: unexpected output '10 a'"
+        );
+        assert_eq!(
+            error(&["$ mkdir a", "$ cd a", "$ pwd", "/b"]),
+            "line 4: pwd printed /b, expected /a"
+        );
+        assert_eq!(
+            error(&["$ mkdir a", "$ cd a", "$ rm /a"]),
+            "line 3: rm: /a is in use"
+        );
+        assert_eq!(
+            error(&["$ cd /", "$ mkdir -p a/b", "$ rm a"]),
+            "line 3: rm: /a is a directory"
+        );
+        assert_eq!(
+            error(&["$ cd /", "$ mkdir a", "$ mkdir a/.."]),
+            "line 3: mkdir: invalid name '..'"
+        );
+        assert_eq!(
+            error(&["$ cd /", "$ mkdir -p a/b", "$ mkdir b/c"]),
+            "line 3: mkdir: no such file or directory 'b' in /"
+        );
+        assert_eq!(
+            error(&["$ cd /", "$ ls", "10 a/b"]),
+            "line 3: invalid name 'a/b'"
+        );
+        assert_eq!(
+            error(&["$ cd /", "$ ls", "dir .."]),
+            "line 3: invalid name '..'"
+        );
+    }
+
+    #[test]
+    fn tree_view() {
+        let fs = parse_input("./src/07/test.txt");
+        let sizes = DirSizes::new(&fs);
+        let expected = [
+            "- / (dir)",
+            "  - a (dir)",
+            "    - e (dir)",
+            "      - i (file, size=584)",
+            "    - f (file, size=29116)",
+            "    - g (file, size=2557)",
+            "    - h.lst (file, size=62596)",
+            "  - b.txt (file, size=14848514)",
+            "  - c.dat (file, size=8504156)",
+            "  - d (dir)",
+            "    - d.ext (file, size=5626152)",
+            "    - d.log (file, size=8033020)",
+            "    - j (file, size=4060174)",
+            "    - k (file, size=7214296)",
+        ];
+        assert_eq!(
+            render_tree(&fs, &sizes, fs.root, SortBy::Name, None),
+            expected.join("\n")
+        );
+
+        let a = fs.lookup("/a").unwrap();
+        assert_eq!(
+            render_tree(&fs, &sizes, a, SortBy::Size, Some(1)),
+            "- a (dir)\n  - h.lst (file, size=62596)\n  - f (file, size=29116)\n  - g (file, size=2557)\n  - e (dir)"
+        );
+    }
+
+    #[test]
+    fn du_listing() {
+        let fs = parse_input("./src/07/test.txt");
+        let sizes = DirSizes::new(&fs);
+        let expected = [
+            "24933642\t/d",
+            "14848514\t/b.txt",
+            "8504156\t/c.dat",
+            "94853\t/a",
+            "48381165\t/",
+        ];
+        assert_eq!(
+            render_du(&fs, &sizes, fs.root, SortBy::Size, Some(1)),
+            expected.join("\n")
+        );
+
+        let e = fs.lookup("/a/e").unwrap();
+        assert_eq!(
+            render_du(&fs, &sizes, e, SortBy::Name, None),
+            "584\t/a/e/i\n584\t/a/e"
+        );
+    }
+
+    #[test]
+    fn real_directory_round_trip() {
+        let fs = parse_input("./src/07/test.txt");
+        let root = std::env::temp_dir().join(format!("aoc-07-{}", std::process::id()));
+        materialise(&fs, &root).unwrap();
+        let transcript = transcript_from_dir(&root);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let transcript = transcript.unwrap();
+        assert!(transcript.starts_with("$ cd /\n$ ls\ndir a\n14848514 b.txt\n"));
+        let copy = parse_transcript(&transcript).unwrap();
+        assert_eq!(
+            render_tree(&copy, &DirSizes::new(&copy), copy.root, SortBy::Name, None),
+            render_tree(&fs, &DirSizes::new(&fs), fs.root, SortBy::Name, None)
+        );
+    }
+
+    #[test]
+    fn materialise_stays_below_root() {
+        let base = std::env::temp_dir().join(format!("aoc-07-escape-{}", std::process::id()));
+        let root = base.join("root");
+        let outside = base.join("outside");
+        let escapes = [
+            Type::File((10, outside.to_str().unwrap().to_string())),
+            Type::File((10, "../outside".to_string())),
+            Type::Dir("..".to_string()),
+            Type::Dir(String::new()),
+        ];
+        for node in escapes {
+            let mut fs = Filesystem::new();
+            fs.add(fs.root, node);
+            let result = materialise(&fs, &root);
+            assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+            assert!(!outside.exists());
+            assert_eq!(std::fs::read_dir(&base).unwrap().count(), 1);
+        }
+
+        // Names with spaces cannot be written as a transcript line
+        std::fs::create_dir(root.join("a b")).unwrap();
+        let transcript = transcript_from_dir(&root);
+        std::fs::remove_dir_all(&base).unwrap();
+        assert_eq!(
+            transcript.unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn shell_session() {
+        let fs = parse_input("./src/07/test.txt");
+        let sizes = DirSizes::new(&fs);
+        let mut shell = Shell::new(&fs, &sizes);
+        let mut run = |line| shell.execute(line);
+
+        assert_eq!(run("pwd"), Ok("/".to_string()));
+        assert_eq!(
+            run("ls"),
+            Ok("dir a\n14848514 b.txt\n8504156 c.dat\ndir d".to_string())
+        );
+        assert_eq!(run("cd a/e"), Ok(String::new()));
+        assert_eq!(run("pwd"), Ok("/a/e".to_string()));
+        assert_eq!(run("ls"), Ok("584 i".to_string()));
+        assert_eq!(run("ls i"), Ok("584 i".to_string()));
+        assert_eq!(
+            run("du .."),
+            Ok(
+                "584\t/a/e/i\n584\t/a/e\n29116\t/a/f\n2557\t/a/g\n62596\t/a/h.lst\n94853\t/a"
+                    .to_string()
+            )
+        );
+        assert_eq!(run("cd"), Ok(String::new()));
+        assert_eq!(run("find -size +10000000"), Ok("/\n/b.txt\n/d".to_string()));
+        assert_eq!(run("find d -size -5000000"), Ok("/d/j".to_string()));
+        assert_eq!(run("find /a -size 584"), Ok("/a/e\n/a/e/i".to_string()));
+        assert_eq!(
+            run("cd b.txt"),
+            Err("/b.txt is not a directory".to_string())
+        );
+        assert_eq!(
+            run("find -size +lots"),
+            Err("find: invalid size '+lots'".to_string())
+        );
+        assert_eq!(
+            run("find -size é"),
+            Err("find: invalid size 'é'".to_string())
+        );
+        assert_eq!(run("rm -r /"), Err("unknown command 'rm -r /'".to_string()));
+        assert_eq!(run("pwd"), Ok("/".to_string()));
+    }
+
+    #[test]
+    fn deep_transcript() {
+        let mut transcript = String::from("$ cd /\n");
+        for _ in 0..100000 {
+            transcript.push_str("$ ls\ndir d\n10 f\n$ cd d\n");
+        }
+        let fs = parse_transcript(&transcript).unwrap();
+        let sizes = DirSizes::new(&fs);
+        assert_eq!(sizes.get(fs.root), 1000000);
+        assert_eq!(sizes.at_most(100).len(), 11);
+        assert_eq!(sizes.smallest_at_least(55).map(|x| x.0), Some(60));
+        assert_eq!(sizes.smallest_at_least(1000001), None);
     }
 }
 
@@ -39,112 +432,890 @@ enum Type {
     File((usize, String)),
 }
 
-fn parse_input(path: &str) -> Graph<Type, i32> {
-    let input = std::fs::read_to_string(path).unwrap();
-    let mut graph = Graph::new();
-    let root = graph.add_node(Type::Dir("/".to_string()));
-    let mut current_dir = root;
-
-    for line in input.lines() {
-        let line: Vec<&str> = line.split(' ').collect();
-        match (line.first().unwrap(), line.get(1).unwrap()) {
-            (&"$", &"ls") => { /* ignore */ }
-            (&"$", &"cd") => {
-                let dir = line.get(2).unwrap();
-                match *dir {
-                    "/" => current_dir = root,
-                    ".." => {
-                        current_dir = graph
-                            .neighbors_directed(current_dir, Direction::Incoming)
-                            .next()
-                            .unwrap();
+// Directory tree with an edge from every directory to each of its children. The graph
+// is stable, so removing nodes does not invalidate the indices of the others.
+struct Filesystem {
+    graph: StableGraph<Type, ()>,
+    root: NodeIndex,
+}
+
+impl Filesystem {
+    fn new() -> Self {
+        let mut graph = StableGraph::new();
+        let root = graph.add_node(Type::Dir("/".to_string()));
+        Filesystem { graph, root }
+    }
+
+    fn name(&self, node: NodeIndex) -> &str {
+        match self.graph.node_weight(node).unwrap() {
+            Type::Dir(name) => name,
+            Type::File((_, name)) => name,
+        }
+    }
+
+    fn is_dir(&self, node: NodeIndex) -> bool {
+        matches!(self.graph.node_weight(node).unwrap(), Type::Dir(_))
+    }
+
+    fn parent(&self, node: NodeIndex) -> Option<NodeIndex> {
+        self.graph
+            .neighbors_directed(node, Direction::Incoming)
+            .next()
+    }
+
+    fn children(&self, node: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.graph.neighbors_directed(node, Direction::Outgoing)
+    }
+
+    fn child(&self, node: NodeIndex, name: &str) -> Option<NodeIndex> {
+        self.graph
+            .neighbors_directed(node, Direction::Outgoing)
+            .find(|child| self.name(*child) == name)
+    }
+
+    fn add(&mut self, parent: NodeIndex, node: Type) -> NodeIndex {
+        let child = self.graph.add_node(node);
+        self.graph.add_edge(parent, child, ());
+        child
+    }
+
+    fn path(&self, node: NodeIndex) -> String {
+        let mut names = vec![];
+        let mut current = node;
+        while let Some(parent) = self.parent(current) {
+            names.push(self.name(current));
+            current = parent;
+        }
+        format!("/{}", names.iter().rev().join("/"))
+    }
+
+    // Follows an absolute path, or one relative to `from`, such as `../x` or `a/b/c`
+    fn resolve(&self, from: NodeIndex, path: &str) -> Result<NodeIndex, String> {
+        let start = if path.starts_with('/') {
+            self.root
+        } else {
+            from
+        };
+        path.split('/').try_fold(start, |node, name| match name {
+            "" | "." => Ok(node),
+            ".." => Ok(self.parent(node).unwrap_or(self.root)),
+            _ if !self.is_dir(node) => Err(format!("{} is not a directory", self.path(node))),
+            name => self.child(node, name).ok_or_else(|| {
+                format!(
+                    "no such file or directory '{}' in {}",
+                    name,
+                    self.path(node)
+                )
+            }),
+        })
+    }
+
+    fn resolve_dir(&self, from: NodeIndex, path: &str) -> Result<NodeIndex, String> {
+        let node = self.resolve(from, path)?;
+        if self.is_dir(node) {
+            Ok(node)
+        } else {
+            Err(format!("{} is not a directory", self.path(node)))
+        }
+    }
+
+    // Finds a node by its absolute path, such as `/a/e`
+    fn lookup(&self, path: &str) -> Option<NodeIndex> {
+        if !path.starts_with('/') {
+            return None;
+        }
+        self.resolve(self.root, path).ok()
+    }
+
+    fn dirs(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.graph.node_indices().filter(|node| self.is_dir(*node))
+    }
+
+    // Adds the directory unless it already exists
+    fn ensure_dir(&mut self, parent: NodeIndex, name: &str) -> Result<NodeIndex, String> {
+        check_name(name)?;
+        match self.child(parent, name) {
+            Some(node) if self.is_dir(node) => Ok(node),
+            Some(node) => Err(format!("{} is a file", self.path(node))),
+            None => Ok(self.add(parent, Type::Dir(name.to_string()))),
+        }
+    }
+
+    // Adds the file, or updates its size when it already exists
+    fn ensure_file(&mut self, parent: NodeIndex, name: &str, size: usize) -> Result<(), String> {
+        check_name(name)?;
+        match self.child(parent, name) {
+            Some(node) if self.is_dir(node) => Err(format!("{} is a directory", self.path(node))),
+            Some(node) => {
+                *self.graph.node_weight_mut(node).unwrap() = Type::File((size, name.to_string()));
+                Ok(())
+            }
+            None => {
+                self.add(parent, Type::File((size, name.to_string())));
+                Ok(())
+            }
+        }
+    }
+
+    // Like `mkdir -p`, adds every directory along the path that does not exist yet
+    fn ensure_path(&mut self, from: NodeIndex, path: &str) -> Result<NodeIndex, String> {
+        let start = if path.starts_with('/') {
+            self.root
+        } else {
+            from
+        };
+        path.split('/').try_fold(start, |node, name| match name {
+            "" | "." => Ok(node),
+            ".." => Ok(self.parent(node).unwrap_or(self.root)),
+            name => self.ensure_dir(node, name),
+        })
+    }
+
+    fn is_ancestor(&self, ancestor: NodeIndex, node: NodeIndex) -> bool {
+        let mut current = Some(node);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.parent(node);
+        }
+        false
+    }
+
+    // Removes the node with everything below it
+    fn remove(&mut self, node: NodeIndex) -> Result<(), String> {
+        if node == self.root {
+            return Err("cannot remove /".to_string());
+        }
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            stack.extend(self.children(node));
+            self.graph.remove_node(node);
+        }
+        Ok(())
+    }
+}
+
+// A name of a single file or directory, which cannot be used to reach any other node
+fn check_name(name: &str) -> Result<(), String> {
+    if matches!(name, "" | "." | "..") || name.contains('/') {
+        Err(format!("invalid name '{}'", name))
+    } else {
+        Ok(())
+    }
+}
+
+// The directory part and the last name of a path
+fn split_path(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rsplit_once('/') {
+        Some(("", name)) => ("/", name),
+        Some((parent, name)) => (parent, name),
+        None => (".", path),
+    }
+}
+
+// What to do with lines that are not commands
+enum Output {
+    Unexpected,
+    Listing,
+    WorkingDirectory,
+}
+
+fn parse_transcript(input: &str) -> Result<Filesystem, String> {
+    let mut fs = Filesystem::new();
+    let mut current_dir = fs.root;
+    let mut output = Output::Unexpected;
+
+    for (index, line) in input.lines().enumerate() {
+        let error = |message: String| format!("line {}: {}", index + 1, message);
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["$", command @ ..] => {
+                output = Output::Unexpected;
+                match command {
+                    ["ls"] => output = Output::Listing,
+                    ["pwd"] => output = Output::WorkingDirectory,
+                    ["cd", path] => {
+                        current_dir = fs
+                            .resolve_dir(current_dir, path)
+                            .map_err(|e| error(format!("cd: {}", e)))?;
+                    }
+                    ["mkdir", path] => {
+                        let (parent, name) = split_path(path);
+                        fs.resolve_dir(current_dir, parent)
+                            .and_then(|parent| fs.ensure_dir(parent, name))
+                            .map_err(|e| error(format!("mkdir: {}", e)))?;
                     }
-                    dir => {
-                        current_dir = graph
-                            .neighbors_directed(current_dir, Direction::Outgoing)
-                            .find(|i| {
-                                let node = graph.node_weight(*i).unwrap();
-                                *node == Type::Dir(dir.to_string())
-                            })
-                            .unwrap();
+                    ["mkdir", "-p", path] => {
+                        fs.ensure_path(current_dir, path)
+                            .map_err(|e| error(format!("mkdir: {}", e)))?;
                     }
+                    ["rm", flags @ .., path] if matches!(flags, [] | ["-r"] | ["-rf"]) => {
+                        let recursive = !flags.is_empty();
+                        let (parent, name) = split_path(path);
+                        let parent = fs
+                            .resolve_dir(current_dir, parent)
+                            .map_err(|e| error(format!("rm: {}", e)))?;
+                        // Removing something that is already gone is fine
+                        if let Some(node) = fs.child(parent, name) {
+                            if fs.is_ancestor(node, current_dir) {
+                                return Err(error(format!("rm: {} is in use", fs.path(node))));
+                            }
+                            if fs.is_dir(node) && !recursive {
+                                return Err(error(format!("rm: {} is a directory", fs.path(node))));
+                            }
+                            fs.remove(node).map_err(|e| error(format!("rm: {}", e)))?;
+                        }
+                    }
+                    _ => return Err(error(format!("unknown command '{}'", command.join(" ")))),
+                }
+            }
+            words => match (&output, words) {
+                (Output::Listing, ["dir", name]) => {
+                    fs.ensure_dir(current_dir, name).map_err(error)?;
+                }
+                (Output::Listing, [size, name]) => {
+                    let size = size
+                        .parse()
+                        .map_err(|_| error(format!("invalid size '{}'", size)))?;
+                    fs.ensure_file(current_dir, name, size).map_err(error)?;
+                }
+                (Output::WorkingDirectory, [path]) => {
+                    if fs.lookup(path) != Some(current_dir) {
+                        return Err(error(format!(
+                            "pwd printed {}, expected {}",
+                            path,
+                            fs.path(current_dir)
+                        )));
+                    }
+                }
+                _ => return Err(error(format!("unexpected output '{}'", line))),
+            },
+        }
+    }
+    Ok(fs)
+}
+
+fn parse_input(path: &str) -> Filesystem {
+    parse_transcript(&std::fs::read_to_string(path).unwrap()).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// Iterative, so deep trees cannot overflow the call stack. Children come before their parents.
+fn post_order(fs: &Filesystem, node: NodeIndex) -> Vec<NodeIndex> {
+    let mut order = vec![];
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        order.push(node);
+        stack.extend(fs.children(node));
+    }
+    order.reverse();
+    order
+}
+
+// Sizes of all directories and files, computed in a single post-order traversal
+struct DirSizes {
+    sizes: Vec<usize>, // by node index
+    sorted: Vec<(usize, NodeIndex)>,
+}
+
+impl DirSizes {
+    fn new(fs: &Filesystem) -> Self {
+        let mut sizes = vec![0; fs.graph.node_bound()];
+        for node in post_order(fs, fs.root) {
+            sizes[node.index()] = match fs.graph.node_weight(node).unwrap() {
+                Type::File((size, _)) => *size,
+                Type::Dir(_) => fs.children(node).map(|child| sizes[child.index()]).sum(),
+            };
+        }
+
+        let sorted = fs
+            .dirs()
+            .map(|dir| (sizes[dir.index()], dir))
+            .sorted()
+            .collect();
+        DirSizes { sizes, sorted }
+    }
+
+    fn get(&self, node: NodeIndex) -> usize {
+        self.sizes[node.index()]
+    }
+
+    fn at_most(&self, max: usize) -> &[(usize, NodeIndex)] {
+        let end = self.sorted.partition_point(|(size, _)| *size <= max);
+        &self.sorted[..end]
+    }
+
+    fn smallest_at_least(&self, min: usize) -> Option<(usize, NodeIndex)> {
+        let start = self.sorted.partition_point(|(size, _)| *size < min);
+        self.sorted.get(start).copied()
+    }
+}
+
+fn part_one(sizes: &DirSizes) -> usize {
+    sizes.at_most(100000).iter().map(|(size, _)| size).sum()
+}
+
+const DISK_SIZE: usize = 70000000;
+const REQUIRED_FREE: usize = 30000000;
+
+// How many bytes have to go before `required_free` bytes are available
+fn bytes_to_free(
+    fs: &Filesystem,
+    sizes: &DirSizes,
+    disk_size: usize,
+    required_free: usize,
+) -> usize {
+    (sizes.get(fs.root) + required_free).saturating_sub(disk_size)
+}
+
+fn smallest_directory_to_delete(
+    fs: &Filesystem,
+    sizes: &DirSizes,
+    disk_size: usize,
+    required_free: usize,
+) -> Option<NodeIndex> {
+    let to_free = bytes_to_free(fs, sizes, disk_size, required_free);
+    sizes.smallest_at_least(to_free).map(|(_, dir)| dir)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Cleanup {
+    SingleDirectory,
+    FewestBytes, // any files and directories, removing as little as possible
+    FewestItems, // any files and directories, ties broken by fewest bytes
+}
+
+#[derive(Debug, PartialEq)]
+struct CleanupPlan {
+    paths: Vec<String>,
+    freed: usize,
+}
+
+fn plan_cleanup(
+    fs: &Filesystem,
+    sizes: &DirSizes,
+    disk_size: usize,
+    required_free: usize,
+    strategy: Cleanup,
+) -> Option<CleanupPlan> {
+    let to_free = bytes_to_free(fs, sizes, disk_size, required_free);
+    if to_free == 0 {
+        return Some(CleanupPlan {
+            paths: vec![],
+            freed: 0,
+        });
+    }
+    if to_free > sizes.get(fs.root) {
+        return None;
+    }
+
+    let nodes = match strategy {
+        Cleanup::SingleDirectory => {
+            vec![smallest_directory_to_delete(
+                fs,
+                sizes,
+                disk_size,
+                required_free,
+            )?]
+        }
+        Cleanup::FewestBytes => fewest_bytes(fs, sizes, to_free)?,
+        Cleanup::FewestItems => fewest_items(fs, sizes, to_free),
+    };
+    Some(CleanupPlan {
+        paths: nodes.iter().map(|node| fs.path(*node)).sorted().collect(),
+        freed: nodes.iter().map(|node| sizes.get(*node)).sum(),
+    })
+}
+
+// Deleting directories only ever deletes files, so this picks the files first and then
+// replaces every directory whose files all go by the directory itself
+fn fewest_bytes(fs: &Filesystem, sizes: &DirSizes, to_free: usize) -> Option<Vec<NodeIndex>> {
+    let files: Vec<NodeIndex> = fs.graph.node_indices().filter(|x| !fs.is_dir(*x)).collect();
+    let file_sizes: Vec<usize> = files.iter().map(|file| sizes.get(*file)).collect();
+    // A single directory is always an option, so only smaller totals are worth searching for
+    let (bound, dir) = sizes.smallest_at_least(to_free)?;
+    let picked: Vec<NodeIndex> = match subset_sum_below(&file_sizes, to_free, bound) {
+        Some(picked) => picked.into_iter().map(|index| files[index]).collect(),
+        None => post_order(fs, dir)
+            .into_iter()
+            .filter(|node| !fs.is_dir(*node))
+            .collect(),
+    };
+
+    let mut deleted = vec![false; fs.graph.node_bound()];
+    for file in picked {
+        deleted[file.index()] = true;
+    }
+    for node in post_order(fs, fs.root) {
+        if fs.is_dir(node) {
+            deleted[node.index()] = fs.children(node).all(|child| deleted[child.index()]);
+        } else if sizes.get(node) == 0 {
+            deleted[node.index()] = true;
+        }
+    }
+
+    let mut nodes = vec![];
+    let mut stack = vec![fs.root];
+    while let Some(dir) = stack.pop() {
+        for child in fs.children(dir) {
+            if deleted[child.index()] {
+                if sizes.get(child) > 0 {
+                    nodes.push(child);
                 }
+            } else if fs.is_dir(child) {
+                stack.push(child);
             }
-            (&"dir", name) => {
-                let dir = graph.add_node(Type::Dir(name.to_string()));
-                graph.add_edge(current_dir, dir, 1);
+        }
+    }
+    Some(nodes)
+}
+
+// Indices of a subset of `sizes` with the smallest total of at least `min`, as long as that
+// total is below `bound`. Branch and bound over the sizes, largest first, so memory only
+// depends on the number of sizes and not on how big they are.
+fn subset_sum_below(sizes: &[usize], min: usize, bound: usize) -> Option<Vec<usize>> {
+    let order: Vec<usize> = (0..sizes.len())
+        .filter(|index| sizes[*index] > 0)
+        .sorted_by_key(|index| std::cmp::Reverse(sizes[*index]))
+        .collect();
+    let size = |position: usize| sizes[order[position]];
+    // What all sizes from a position onwards add up to
+    let mut remaining = vec![0; order.len() + 1];
+    for position in (0..order.len()).rev() {
+        remaining[position] = remaining[position + 1] + size(position);
+    }
+
+    let mut best = (bound, None);
+    let mut picked: Vec<usize> = vec![];
+    // Every entry adds one position to the first `depth` picked ones, then tries the
+    // positions from `next` onwards
+    let mut stack = vec![(0, None, 0, 0)];
+    while let Some((depth, position, next, total)) = stack.pop() {
+        picked.truncate(depth);
+        picked.extend(position);
+        if total + remaining[next] < min {
+            continue;
+        }
+
+        // Any of the larger sizes reaches `min` right away, and the smallest of those is best
+        let enough = next + order[next..].partition_point(|x| total + sizes[*x] >= min);
+        if enough > next && total + size(enough - 1) < best.0 {
+            let mut subset = picked.clone();
+            subset.push(enough - 1);
+            best = (total + size(enough - 1), Some(subset));
+            if best.0 == min {
+                break;
             }
-            (size, name) => {
-                let file = graph.add_node(Type::File((size.parse().unwrap(), name.to_string())));
-                graph.add_edge(current_dir, file, 1);
+        }
+
+        // The others need more sizes after them. Equal sizes give the same totals, so only
+        // the first of them is tried at this depth.
+        for position in (enough..order.len()).rev() {
+            let repeated = position > enough && size(position) == size(position - 1);
+            if !repeated {
+                stack.push((
+                    picked.len(),
+                    Some(position),
+                    position + 1,
+                    total + size(position),
+                ));
             }
         }
     }
-    graph
+    best.1
+        .map(|subset| subset.into_iter().map(|position| order[position]).collect())
 }
 
-fn node_index(graph: &Graph<Type, i32>, name: &str) -> NodeIndex {
-    graph
+fn fewest_items(fs: &Filesystem, sizes: &DirSizes, to_free: usize) -> Vec<NodeIndex> {
+    // Items below the same top level directory never beat that directory, so the largest
+    // top level entries tell how many items are needed
+    let mut count = 0;
+    let mut freed = 0;
+    for child in fs
+        .children(fs.root)
+        .sorted_by_key(|x| std::cmp::Reverse(sizes.get(*x)))
+    {
+        if freed >= to_free {
+            break;
+        }
+        freed += sizes.get(child);
+        count += 1;
+    }
+
+    let candidates: Vec<(usize, NodeIndex)> = fs
+        .graph
         .node_indices()
-        .find(|i| graph.node_weight(*i).unwrap() == &Type::Dir(name.to_string()))
-        .unwrap()
+        .filter(|node| *node != fs.root)
+        .map(|node| (sizes.get(node), node))
+        .filter(|(size, _)| *size > 0)
+        .sorted_by_key(|(size, _)| std::cmp::Reverse(*size))
+        .collect();
+    let mut best = (usize::MAX, vec![]);
+    pick_items(fs, &candidates, to_free, count, &mut vec![], 0, &mut best);
+    best.1
+}
+
+// Branch and bound over the candidates, largest first, choosing `count` unrelated items
+fn pick_items(
+    fs: &Filesystem,
+    candidates: &[(usize, NodeIndex)],
+    to_free: usize,
+    count: usize,
+    picked: &mut Vec<NodeIndex>,
+    freed: usize,
+    best: &mut (usize, Vec<NodeIndex>),
+) {
+    if picked.len() == count {
+        if freed >= to_free && freed < best.0 {
+            *best = (freed, picked.clone());
+        }
+        return;
+    }
+    let remaining = count - picked.len();
+    for (index, &(size, node)) in candidates.iter().enumerate() {
+        // Nothing after this can still reach the target
+        let most: usize = candidates[index..]
+            .iter()
+            .take(remaining)
+            .map(|x| x.0)
+            .sum();
+        if freed + most < to_free {
+            break;
+        }
+        if freed + size >= best.0 {
+            continue;
+        }
+        let related = picked
+            .iter()
+            .any(|other| fs.is_ancestor(*other, node) || fs.is_ancestor(node, *other));
+        if related {
+            continue;
+        }
+        picked.push(node);
+        pick_items(
+            fs,
+            &candidates[index + 1..],
+            to_free,
+            count,
+            picked,
+            freed + size,
+            best,
+        );
+        picked.pop();
+    }
 }
 
-fn dir_size(graph: &Graph<Type, i32>, node: NodeIndex) -> usize {
-    let children = graph.neighbors_directed(node, Direction::Outgoing);
-    let mut total = 0;
-    for child in children {
-        let child_node = graph.node_weight(child).unwrap();
-        match child_node {
-            Type::Dir(_) => {
-                total += dir_size(graph, child);
+#[derive(Clone, Copy)]
+enum SortBy {
+    Name,
+    Size, // largest first
+}
+
+fn sorted_children(
+    fs: &Filesystem,
+    sizes: &DirSizes,
+    node: NodeIndex,
+    sort_by: SortBy,
+) -> Vec<NodeIndex> {
+    let children = fs.children(node).sorted_by_key(|child| fs.name(*child));
+    match sort_by {
+        SortBy::Name => children.collect(),
+        SortBy::Size => children
+            .sorted_by_key(|child| std::cmp::Reverse(sizes.get(*child)))
+            .collect(),
+    }
+}
+
+// Indented view in the format of the puzzle, such as `- a (dir)`
+fn render_tree(
+    fs: &Filesystem,
+    sizes: &DirSizes,
+    node: NodeIndex,
+    sort_by: SortBy,
+    max_depth: Option<usize>,
+) -> String {
+    let mut lines = vec![];
+    let mut stack = vec![(node, 0)];
+    while let Some((node, depth)) = stack.pop() {
+        let indent = "  ".repeat(depth);
+        match fs.graph.node_weight(node).unwrap() {
+            Type::Dir(name) => lines.push(format!("{}- {} (dir)", indent, name)),
+            Type::File((size, name)) => {
+                lines.push(format!("{}- {} (file, size={})", indent, name, size))
             }
-            Type::File((size, _)) => {
-                total += size;
+        }
+        if max_depth.is_none_or(|max_depth| depth < max_depth) {
+            let children = sorted_children(fs, sizes, node, sort_by);
+            stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+        }
+    }
+    lines.join("\n")
+}
+
+// Like `du -a`: cumulative sizes, with every directory after its contents
+fn render_du(
+    fs: &Filesystem,
+    sizes: &DirSizes,
+    node: NodeIndex,
+    sort_by: SortBy,
+    max_depth: Option<usize>,
+) -> String {
+    let mut lines = vec![];
+    let mut stack = vec![(node, 0, false)];
+    while let Some((node, depth, children_done)) = stack.pop() {
+        let within_depth = max_depth.is_none_or(|max_depth| depth < max_depth);
+        if children_done || !fs.is_dir(node) || !within_depth {
+            lines.push(format!("{}\t{}", sizes.get(node), fs.path(node)));
+        } else {
+            stack.push((node, depth, true));
+            let children = sorted_children(fs, sizes, node, sort_by);
+            stack.extend(
+                children
+                    .into_iter()
+                    .rev()
+                    .map(|child| (child, depth + 1, false)),
+            );
+        }
+    }
+    lines.join("\n")
+}
+
+// Recreates the filesystem below `root`, with sparse files so no disk space is taken
+fn materialise(fs: &Filesystem, root: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(root)?;
+    let mut stack = vec![(fs.root, root.to_path_buf())];
+    while let Some((dir, path)) = stack.pop() {
+        for child in fs.children(dir) {
+            // Anything else could point outside `root`, such as `..` or an absolute path
+            check_name(fs.name(child))
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            let child_path = path.join(fs.name(child));
+            match fs.graph.node_weight(child).unwrap() {
+                Type::Dir(_) => {
+                    std::fs::create_dir(&child_path)?;
+                    stack.push((child, child_path));
+                }
+                Type::File((size, _)) => File::create(&child_path)?.set_len(*size as u64)?,
             }
         }
     }
-    total
+    Ok(())
 }
 
-fn part_one(graph: &Graph<Type, i32>) -> usize {
-    graph
-        .node_indices()
-        .filter(|i| {
-            let node = graph.node_weight(*i).unwrap();
-            matches!(node, Type::Dir(_))
-        })
-        .map(|i| dir_size(graph, i))
-        .filter(|size| *size <= 100000)
-        .sum()
+// A transcript that lists every directory below `root`, in name order. Anything that is
+// neither a file nor a directory, such as a symlink, is left out. Names the transcript
+// cannot hold, with whitespace or invalid UTF-8, are an error.
+fn transcript_from_dir(root: &Path) -> std::io::Result<String> {
+    let mut lines = vec!["$ cd /".to_string()];
+    // `None` marks the way back up after a directory is done
+    let mut stack = vec![Some(("/".to_string(), root.to_path_buf()))];
+    while let Some(entry) = stack.pop() {
+        let Some((name, path)) = entry else {
+            lines.push("$ cd ..".to_string());
+            continue;
+        };
+        if path != root {
+            lines.push(format!("$ cd {}", name));
+            stack.push(None);
+        }
+
+        let mut subdirs = vec![];
+        lines.push("$ ls".to_string());
+        for entry in std::fs::read_dir(&path)?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .sorted_by_key(|entry| entry.file_name())
+        {
+            let name = entry
+                .file_name()
+                .into_string()
+                .ok()
+                .filter(|name| !name.contains(char::is_whitespace))
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("cannot list {} in a transcript", entry.path().display()),
+                    )
+                })?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                lines.push(format!("dir {}", name));
+                subdirs.push(Some((name, entry.path())));
+            } else if file_type.is_file() {
+                lines.push(format!("{} {}", entry.metadata()?.len(), name));
+            }
+        }
+        stack.extend(subdirs.into_iter().rev());
+    }
+    Ok(lines.join("\n") + "\n")
 }
 
-fn smallest_directory_to_delete(graph: &Graph<Type, i32>) -> usize {
-    let root = node_index(graph, "/");
-    let max_allowed = 40000000;
-    let used = dir_size(graph, root);
-    let to_free = used - max_allowed;
+// Answers shell commands from the parsed filesystem instead of a real one
+struct Shell<'a> {
+    fs: &'a Filesystem,
+    sizes: &'a DirSizes,
+    cwd: NodeIndex,
+}
 
-    graph
-        .node_indices()
-        .filter(|i| {
-            let node = graph.node_weight(*i).unwrap();
-            matches!(node, Type::Dir(_))
-        })
-        .map(|i| (dir_size(graph, i), i))
-        .map(|(size, _)| size)
-        .sorted()
-        .find(|size| *size >= to_free)
-        .unwrap()
+impl<'a> Shell<'a> {
+    fn new(fs: &'a Filesystem, sizes: &'a DirSizes) -> Self {
+        Shell {
+            fs,
+            sizes,
+            cwd: fs.root,
+        }
+    }
+
+    fn listing_line(&self, node: NodeIndex) -> String {
+        match self.fs.graph.node_weight(node).unwrap() {
+            Type::Dir(name) => format!("dir {}", name),
+            Type::File((size, name)) => format!("{} {}", size, name),
+        }
+    }
+
+    // `node` and everything below it whose size matches, in name order
+    fn find(&self, node: NodeIndex, matches: impl Fn(usize) -> bool) -> Vec<String> {
+        let mut found = vec![];
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if matches(self.sizes.get(node)) {
+                found.push(self.fs.path(node));
+            }
+            let children = sorted_children(self.fs, self.sizes, node, SortBy::Name);
+            stack.extend(children.into_iter().rev());
+        }
+        found
+    }
+
+    fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let resolve = |path: Option<&&str>| self.fs.resolve(self.cwd, path.unwrap_or(&"."));
+        match words.as_slice() {
+            [] => Ok(String::new()),
+            ["cd"] => {
+                self.cwd = self.fs.root;
+                Ok(String::new())
+            }
+            ["cd", path] => {
+                self.cwd = self.fs.resolve_dir(self.cwd, path)?;
+                Ok(String::new())
+            }
+            ["pwd"] => Ok(self.fs.path(self.cwd)),
+            ["ls", path @ ..] if path.len() <= 1 => {
+                let node = resolve(path.first())?;
+                if !self.fs.is_dir(node) {
+                    return Ok(self.listing_line(node));
+                }
+                let children = sorted_children(self.fs, self.sizes, node, SortBy::Name);
+                Ok(children.iter().map(|x| self.listing_line(*x)).join("\n"))
+            }
+            ["du", path @ ..] if path.len() <= 1 => {
+                let node = resolve(path.first())?;
+                Ok(render_du(self.fs, self.sizes, node, SortBy::Name, None))
+            }
+            ["find", path @ .., "-size", size] if path.len() <= 1 => {
+                let node = resolve(path.first())?;
+                let invalid = || format!("find: invalid size '{}'", size);
+                let parse = |n: &str| n.parse::<usize>().map_err(|_| invalid());
+                let found = if let Some(n) = size.strip_prefix('+') {
+                    let n = parse(n)?;
+                    self.find(node, |size| size > n)
+                } else if let Some(n) = size.strip_prefix('-') {
+                    let n = parse(n)?;
+                    self.find(node, |size| size < n)
+                } else {
+                    let n = parse(size)?;
+                    self.find(node, |size| size == n)
+                };
+                Ok(found.join("\n"))
+            }
+            _ => Err(format!("unknown command '{}'", line.trim())),
+        }
+    }
+}
+
+fn run_shell(fs: &Filesystem, sizes: &DirSizes) {
+    let mut shell = Shell::new(fs, sizes);
+    let mut stdout = std::io::stdout();
+    loop {
+        print!("{}$ ", fs.path(shell.cwd));
+        stdout.flush().unwrap();
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line).unwrap() == 0 {
+            println!();
+            break;
+        }
+        if matches!(line.trim(), "exit" | "quit") {
+            break;
+        }
+        match shell.execute(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(error) => println!("error: {}", error),
+        }
+    }
 }
 
 fn main() {
     println!("Hello, day 7!");
 
-    let graph = parse_input("./input/07/input.txt");
-    let part_one_sum = part_one(&graph);
+    // `cargo run --bin 07 -- shell [transcript]` explores a transcript interactively
+    let args: Vec<String> = std::env::args().collect();
+    if let Some("shell") = args.get(1).map(String::as_str) {
+        let fs = parse_input(args.get(2).map_or("./input/07/input.txt", String::as_str));
+        run_shell(&fs, &DirSizes::new(&fs));
+        return;
+    }
+
+    // `cargo run --bin 07 -- transcript <dir>` writes a transcript for a real directory
+    if let (Some("transcript"), Some(dir)) = (args.get(1).map(String::as_str), args.get(2)) {
+        print!("{}", transcript_from_dir(Path::new(dir)).unwrap());
+        return;
+    }
+
+    let fs = parse_input("./input/07/input.txt");
+    let sizes = DirSizes::new(&fs);
+
+    // `cargo run --bin 07 -- materialise <dir>` recreates the input below <dir>
+    if let (Some("materialise"), Some(dir)) = (args.get(1).map(String::as_str), args.get(2)) {
+        materialise(&fs, Path::new(dir)).unwrap();
+        return;
+    }
+
+    // `cargo run --bin 07 -- <tree|du> [name|size] [depth]` shows the filesystem instead
+    if let Some(view @ ("tree" | "du")) = args.get(1).map(String::as_str) {
+        let sort_by = match args.get(2).map(String::as_str) {
+            Some("size") => SortBy::Size,
+            _ => SortBy::Name,
+        };
+        let max_depth = args.get(3).map(|depth| depth.parse().unwrap());
+        let render = if view == "tree" {
+            render_tree
+        } else {
+            render_du
+        };
+        println!("{}", render(&fs, &sizes, fs.root, sort_by, max_depth));
+        return;
+    }
+
+    let part_one_sum = part_one(&sizes);
     println!("Part 1: {}", part_one_sum);
 
-    let part_two = smallest_directory_to_delete(&graph);
-    println!("Part 2: {}", part_two);
+    let dir = smallest_directory_to_delete(&fs, &sizes, DISK_SIZE, REQUIRED_FREE).unwrap();
+    println!("Part 2: {}", sizes.get(dir));
+    println!("Directory to delete: {}", fs.path(dir));
+
+    for strategy in [
+        Cleanup::SingleDirectory,
+        Cleanup::FewestBytes,
+        Cleanup::FewestItems,
+    ] {
+        let plan = plan_cleanup(&fs, &sizes, DISK_SIZE, REQUIRED_FREE, strategy).unwrap();
+        println!("{:?}: free {} by deleting", strategy, plan.freed);
+        for path in plan.paths {
+            println!("  {}", path);
+        }
+    }
 }
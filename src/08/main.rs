@@ -1,6 +1,7 @@
 extern crate core;
 
-use ndarray::{ArrayView, Axis};
+use itertools::Itertools;
+use ndarray::{Array2, ArrayView, Axis};
 
 #[cfg(test)]
 mod tests {
@@ -9,18 +10,19 @@ mod tests {
     #[test]
     fn test_visible_sides() {
         let forest = parse_input("./src/08/test.txt");
-        assert_eq!(visible_sides_and_score(&forest, [1, 1]).0, 2);
-        assert_eq!(visible_sides_and_score(&forest, [1, 2]).0, 2);
-        assert_eq!(visible_sides_and_score(&forest, [1, 3]).0, 0);
-        assert_eq!(visible_sides_and_score(&forest, [2, 1]).0, 1);
-        assert_eq!(visible_sides_and_score(&forest, [2, 2]).0, 0);
-        assert_eq!(visible_sides_and_score(&forest, [2, 3]).0, 1);
-        assert_eq!(visible_sides_and_score(&forest, [3, 1]).0, 0);
-        assert_eq!(visible_sides_and_score(&forest, [3, 2]).0, 2);
-        assert_eq!(visible_sides_and_score(&forest, [3, 3]).0, 0);
+        let views = tree_views(&forest);
+        assert_eq!(views[[1, 1]].visible_sides(), 2);
+        assert_eq!(views[[1, 2]].visible_sides(), 2);
+        assert_eq!(views[[1, 3]].visible_sides(), 0);
+        assert_eq!(views[[2, 1]].visible_sides(), 1);
+        assert_eq!(views[[2, 2]].visible_sides(), 0);
+        assert_eq!(views[[2, 3]].visible_sides(), 1);
+        assert_eq!(views[[3, 1]].visible_sides(), 0);
+        assert_eq!(views[[3, 2]].visible_sides(), 2);
+        assert_eq!(views[[3, 3]].visible_sides(), 0);
 
-        assert_eq!(visible_sides_and_score(&forest, [0, 0]).0, 2);
-        assert_eq!(visible_sides_and_score(&forest, [4, 3]).0, 4);
+        assert_eq!(views[[0, 0]].visible_sides(), 2);
+        assert_eq!(views[[4, 3]].visible_sides(), 4);
     }
 
     #[test]
@@ -32,8 +34,9 @@ mod tests {
     #[test]
     fn test_scenic_score() {
         let forest = parse_input("./src/08/test.txt");
-        assert_eq!(visible_sides_and_score(&forest, [1, 2]).1, 4);
-        assert_eq!(visible_sides_and_score(&forest, [3, 2]).1, 8);
+        let views = tree_views(&forest);
+        assert_eq!(views[[1, 2]].score(), 4);
+        assert_eq!(views[[3, 2]].score(), 8);
     }
 
     #[test]
@@ -41,13 +44,166 @@ mod tests {
         let forest = parse_input("./src/08/test.txt");
         assert_eq!(highest_score(&forest), 8);
     }
+
+    #[test]
+    fn rectangular_forest() {
+        let forest = parse_forest("30373\n25512\n65332\n");
+        assert_eq!(forest.dim(), (3, 5));
+        assert_eq!(visible(&forest), 14);
+        assert_eq!(highest_score(&forest), 2);
+
+        let tall = parse_forest("3\n5\n1\n4\n");
+        assert_eq!(visible(&tall), 4);
+        let edge = Sight {
+            distance: 0,
+            blocked: false,
+        };
+        let blocked = Sight {
+            distance: 1,
+            blocked: true,
+        };
+        assert_eq!(
+            tree_views(&tall)[[2, 0]],
+            TreeView {
+                west: edge,
+                east: edge,
+                north: blocked,
+                south: blocked
+            }
+        );
+    }
+
+    #[test]
+    fn view_record() {
+        let forest = parse_input("./src/08/test.txt");
+        let view = tree_views(&forest)[[3, 2]];
+        let sight = |distance, blocked| Sight { distance, blocked };
+        assert_eq!(view.west, sight(2, false));
+        assert_eq!(view.east, sight(2, true));
+        assert_eq!(view.north, sight(2, true));
+        assert_eq!(view.south, sight(1, false));
+        assert_eq!(view.visible_sides(), 2);
+        assert_eq!(view.score(), 8);
+    }
+
+    #[test]
+    fn heatmaps() {
+        let forest = parse_forest("30373\n25512\n65332\n");
+        let views = tree_views(&forest);
+        assert_eq!(
+            to_pgm(&visibility_mask(&views)),
+            "P2\n5 3\n1\n1 1 1 1 1\n1 1 1 0 1\n1 1 1 1 1\n"
+        );
+        assert_eq!(
+            to_csv(&scenic_scores(&views)),
+            "0,0,0,0,0\n0,1,2,1,0\n0,0,0,0,0\n"
+        );
+
+        let field = Array2::from_shape_vec((1, 3), vec![0, 65536, 131072]).unwrap();
+        assert_eq!(to_pgm(&field), "P2\n3 1\n65535\n0 32767 65535\n");
+    }
+
+    #[test]
+    fn directions() {
+        assert_eq!(slopes(1).len(), 8);
+        assert_eq!(slopes(2).len(), 16);
+        assert!(slopes(3).contains(&[-2, 3]));
+        assert!(!slopes(3).contains(&[2, 2]));
+    }
+
+    #[test]
+    fn bresenham_rays() {
+        let ray = |start, step| ray((5, 6), start, step).collect::<Vec<_>>();
+        assert_eq!(ray([2, 2], [0, 1]), vec![[2, 3], [2, 4], [2, 5]]);
+        assert_eq!(ray([2, 2], [-1, -1]), vec![[1, 1], [0, 0]]);
+        assert_eq!(
+            ray([0, 0], [1, 2]),
+            vec![[1, 1], [1, 2], [2, 3], [2, 4], [3, 5]]
+        );
+        assert_eq!(ray([4, 5], [-3, -1]), vec![[3, 5], [2, 4], [1, 4], [0, 4]]);
+        assert!(ray([0, 3], [-1, 0]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "a ray needs a step that moves")]
+    fn ray_without_direction() {
+        let forest = parse_input("./src/08/test.txt");
+        visible_from(&forest, [2, 2], &[[0, 0]]);
+    }
+
+    #[test]
+    fn visible_at_any_angle() {
+        let forest = parse_input("./src/08/test.txt");
+        let axes = [[0, -1], [0, 1], [-1, 0], [1, 0]];
+        assert_eq!(visible_at_angles(&forest, &axes), visible(&forest));
+        // The 4 in the fourth row can see out diagonally to the north east
+        assert!(!visible_from_outside(&forest, [3, 3], &axes));
+        assert!(visible_from_outside(&forest, [3, 3], &slopes(1)));
+        assert_eq!(visible_at_angles(&forest, &slopes(1)), 22);
+    }
+
+    #[test]
+    fn observation_tower() {
+        let forest = parse_input("./src/08/test.txt");
+        let axes = [[0, -1], [0, 1], [-1, 0], [1, 0]];
+        assert_eq!(
+            visible_from(&forest, [3, 2], &axes),
+            vec![[1, 2], [2, 2], [3, 0], [3, 1], [3, 3], [3, 4], [4, 2]]
+        );
+        assert_eq!(visible_from(&forest, [3, 2], &slopes(1)).len(), 12);
+    }
+
+    // Walks every direction tree by tree, like the puzzle describes it
+    fn look_around(forest: &Array2<u8>, row: usize, col: usize) -> (usize, usize) {
+        let (rows, cols) = forest.dim();
+        let lines: [Vec<[usize; 2]>; 4] = [
+            (0..col).rev().map(|x| [row, x]).collect(),
+            (col + 1..cols).map(|x| [row, x]).collect(),
+            (0..row).rev().map(|y| [y, col]).collect(),
+            (row + 1..rows).map(|y| [y, col]).collect(),
+        ];
+        let mut visible_sides = 0;
+        let mut score = 1;
+        for line in lines {
+            match line.iter().position(|x| forest[*x] >= forest[[row, col]]) {
+                Some(blocker) => score *= blocker + 1,
+                None => {
+                    visible_sides += 1;
+                    score *= line.len();
+                }
+            }
+        }
+        (visible_sides, score)
+    }
+
+    #[test]
+    fn matches_looking_around() {
+        let mut seed: u32 = 2022;
+        let forest = Array2::from_shape_fn((37, 53), |_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % 10) as u8
+        });
+        let views = tree_views(&forest);
+        for ((row, col), view) in views.indexed_iter() {
+            assert_eq!(
+                (view.visible_sides(), view.score()),
+                look_around(&forest, row, col),
+                "[{}, {}]",
+                row,
+                col
+            );
+        }
+    }
+}
+
+fn parse_input(path: &str) -> Array2<u8> {
+    parse_forest(&std::fs::read_to_string(path).unwrap())
 }
 
-fn parse_input(path: &str) -> ndarray::Array2<u8> {
-    let input = std::fs::read_to_string(path).unwrap();
+fn parse_forest(input: &str) -> Array2<u8> {
     let cols = input.lines().next().unwrap().chars().count();
 
-    let mut forest = ndarray::Array2::zeros((0, cols));
+    let mut forest = Array2::zeros((0, cols));
     for line in input.lines() {
         let chars: Vec<u8> = line
             .chars()
@@ -61,80 +217,262 @@ fn parse_input(path: &str) -> ndarray::Array2<u8> {
     forest
 }
 
-fn visible_sides_and_score(forest: &ndarray::Array2<u8>, coordinate: [usize; 2]) -> (usize, usize) {
-    let dim = forest.dim();
-    let value = forest[coordinate];
-    let mut visible_sides = 4;
-    // same row to the west
-    let mut west = 0;
-    for x in (0..coordinate[1]).rev() {
-        west += 1;
-        if forest[[coordinate[0], x]] >= value {
-            visible_sides -= 1;
-            break;
-        }
+#[derive(Clone, Copy)]
+enum Direction {
+    West,
+    East,
+    North,
+    South,
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::West,
+    Direction::East,
+    Direction::North,
+    Direction::South,
+];
+
+// How far a tree can see in one direction. Without a blocker the view reaches the edge.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Sight {
+    distance: usize,
+    blocked: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct TreeView {
+    west: Sight,
+    east: Sight,
+    north: Sight,
+    south: Sight,
+}
+
+impl TreeView {
+    fn sights(&self) -> [Sight; 4] {
+        [self.west, self.east, self.north, self.south]
     }
-    // same row to the east
-    let mut east = 0;
-    for x in (coordinate[1] + 1)..dim.1 {
-        east += 1;
-        if forest[[coordinate[0], x]] >= value {
-            visible_sides -= 1;
-            break;
-        }
+
+    fn visible_sides(&self) -> usize {
+        self.sights().iter().filter(|sight| !sight.blocked).count()
     }
-    // same column to the north
-    let mut north = 0;
-    for y in (0..coordinate[0]).rev() {
-        north += 1;
-        if forest[[y, coordinate[1]]] >= value {
-            visible_sides -= 1;
-            break;
-        }
+
+    fn score(&self) -> usize {
+        self.sights().iter().map(|sight| sight.distance).product()
     }
-    // same column to the south
-    let mut south = 0;
-    for y in (coordinate[0] + 1)..dim.0 {
-        south += 1;
-        if forest[[y, coordinate[1]]] >= value {
-            visible_sides -= 1;
-            break;
+}
+
+// What every tree sees towards `direction`
+fn sweep(forest: &Array2<u8>, direction: Direction) -> Array2<Sight> {
+    let (rows, cols) = forest.dim();
+    let lines: Vec<Vec<[usize; 2]>> = match direction {
+        Direction::West => (0..rows)
+            .map(|row| (0..cols).map(|col| [row, col]).collect())
+            .collect(),
+        Direction::East => (0..rows)
+            .map(|row| (0..cols).rev().map(|col| [row, col]).collect())
+            .collect(),
+        Direction::North => (0..cols)
+            .map(|col| (0..rows).map(|row| [row, col]).collect())
+            .collect(),
+        Direction::South => (0..cols)
+            .map(|col| (0..rows).rev().map(|row| [row, col]).collect())
+            .collect(),
+    };
+
+    let mut result = Array2::default((rows, cols));
+    for line in lines {
+        // Positions along the line of trees that are not yet hidden behind a taller one,
+        // so their heights never increase from the bottom up
+        let mut stack: Vec<(u8, usize)> = vec![];
+        for (position, coordinate) in line.into_iter().enumerate() {
+            let height = forest[coordinate];
+            while stack.last().is_some_and(|(other, _)| *other < height) {
+                stack.pop();
+            }
+            result[coordinate] = match stack.last() {
+                Some((_, blocker)) => Sight {
+                    distance: position - blocker,
+                    blocked: true,
+                },
+                None => Sight {
+                    distance: position,
+                    blocked: false,
+                },
+            };
+            stack.push((height, position));
         }
     }
-    (visible_sides, west * east * north * south)
+    result
 }
 
-fn visible(forest: &ndarray::Array2<u8>) -> usize {
-    let (rows, cols) = forest.dim();
-    let mut visible_trees = 0;
-    for i in 0..cols {
-        for j in 0..rows {
-            if visible_sides_and_score(&forest, [i, j]).0 > 0 {
-                visible_trees += 1;
+fn tree_views(forest: &Array2<u8>) -> Array2<TreeView> {
+    let [west, east, north, south] = DIRECTIONS.map(|direction| sweep(forest, direction));
+    Array2::from_shape_fn(forest.dim(), |coordinate| TreeView {
+        west: west[coordinate],
+        east: east[coordinate],
+        north: north[coordinate],
+        south: south[coordinate],
+    })
+}
+
+fn visible(forest: &Array2<u8>) -> usize {
+    tree_views(forest)
+        .iter()
+        .filter(|view| view.visible_sides() > 0)
+        .count()
+}
+
+fn highest_score(forest: &Array2<u8>) -> usize {
+    tree_views(forest)
+        .iter()
+        .map(TreeView::score)
+        .max()
+        .unwrap_or(0)
+}
+
+fn visibility_mask(views: &Array2<TreeView>) -> Array2<usize> {
+    views.map(|view| (view.visible_sides() > 0) as usize)
+}
+
+fn scenic_scores(views: &Array2<TreeView>) -> Array2<usize> {
+    views.map(TreeView::score)
+}
+
+// Plain (P2) greymap, scaled down when the values do not fit in 16 bits
+fn to_pgm(field: &Array2<usize>) -> String {
+    let (rows, cols) = field.dim();
+    let max = field.iter().copied().max().unwrap_or(0).max(1);
+    let maxval = max.min(65535);
+    let mut lines = vec![
+        "P2".to_string(),
+        format!("{} {}", cols, rows),
+        maxval.to_string(),
+    ];
+    for row in field.rows() {
+        lines.push(row.iter().map(|value| value * maxval / max).join(" "));
+    }
+    lines.join("\n") + "\n"
+}
+
+fn to_csv(field: &Array2<usize>) -> String {
+    field
+        .rows()
+        .into_iter()
+        .map(|row| row.iter().join(",") + "\n")
+        .collect()
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// One step [rows, cols] for every direction with slope p/q where |p| and |q| are at most
+// `max`, so `slopes(1)` gives the eight compass directions
+fn slopes(max: i64) -> Vec<[i64; 2]> {
+    let mut steps = vec![];
+    for rows in -max..=max {
+        for cols in -max..=max {
+            if gcd(rows.abs(), cols.abs()) == 1 {
+                steps.push([rows, cols]);
             }
         }
     }
-    visible_trees
+    steps
 }
 
-fn highest_score(forest: &ndarray::Array2<u8>) -> usize {
-    let mut highest = 0;
-    let (rows, cols) = forest.dim();
-    for i in 0..cols {
-        for j in 0..rows {
-            let score = visible_sides_and_score(&forest, [i, j]).1;
-            if score > highest {
-                highest = score;
+// The trees along a line from `start` (not included) to the edge of the forest. Like
+// Bresenham, it moves one tree at a time along the longer axis and rounds the other.
+fn ray(dim: (usize, usize), start: [usize; 2], step: [i64; 2]) -> impl Iterator<Item = [usize; 2]> {
+    let major = step[0].abs().max(step[1].abs());
+    assert!(major > 0, "a ray needs a step that moves");
+    let offset = move |t: i64, d: i64| d.signum() * ((2 * t * d.abs() + major) / (2 * major));
+    (1..)
+        .map(move |t| {
+            [
+                start[0] as i64 + offset(t, step[0]),
+                start[1] as i64 + offset(t, step[1]),
+            ]
+        })
+        .take_while(move |[row, col]| {
+            (0..dim.0 as i64).contains(row) && (0..dim.1 as i64).contains(col)
+        })
+        .map(|[row, col]| [row as usize, col as usize])
+}
+
+fn visible_from_outside(forest: &Array2<u8>, tree: [usize; 2], steps: &[[i64; 2]]) -> bool {
+    steps
+        .iter()
+        .any(|step| ray(forest.dim(), tree, *step).all(|other| forest[other] < forest[tree]))
+}
+
+fn visible_at_angles(forest: &Array2<u8>, steps: &[[i64; 2]]) -> usize {
+    forest
+        .indexed_iter()
+        .filter(|((row, col), _)| visible_from_outside(forest, [*row, *col], steps))
+        .count()
+}
+
+// Every tree the observer sees along the given directions, up to the first one that is at
+// least as tall as the observer
+fn visible_from(forest: &Array2<u8>, observer: [usize; 2], steps: &[[i64; 2]]) -> Vec<[usize; 2]> {
+    let mut seen = vec![];
+    for step in steps {
+        for tree in ray(forest.dim(), observer, *step) {
+            seen.push(tree);
+            if forest[tree] >= forest[observer] {
+                break;
             }
         }
     }
-    highest
+    seen.sort();
+    seen.dedup();
+    seen
 }
 
 fn main() {
     println!("Hello, day 8!");
 
     let input = parse_input("./input/08/input.txt");
+
+    // `cargo run --bin 08 -- <visibility|score> <pgm|csv> <file>` exports a heatmap instead
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(field @ ("visibility" | "score")) = args.get(1).map(String::as_str) {
+        let views = tree_views(&input);
+        let field = if field == "visibility" {
+            visibility_mask(&views)
+        } else {
+            scenic_scores(&views)
+        };
+        let contents = match args.get(2).map(String::as_str) {
+            Some("csv") => to_csv(&field),
+            _ => to_pgm(&field),
+        };
+        std::fs::write(args.get(3).expect("missing output file"), contents).unwrap();
+        return;
+    }
+
+    // `cargo run --bin 08 -- angles <max>` looks along every slope p/q up to max
+    if let Some("angles") = args.get(1).map(String::as_str) {
+        let max = args.get(2).map_or(1, |max| max.parse().unwrap());
+        let steps = slopes(max);
+        println!(
+            "Visible along {} directions: {}",
+            steps.len(),
+            visible_at_angles(&input, &steps)
+        );
+        let (seen, tower) = input
+            .indexed_iter()
+            .map(|((row, col), _)| (visible_from(&input, [row, col], &steps).len(), [row, col]))
+            .max()
+            .unwrap();
+        println!("Best observation tower: {:?} sees {} trees", tower, seen);
+        return;
+    }
+
     let visible_trees = visible(&input);
     println!("Part 1: {}", visible_trees);
 
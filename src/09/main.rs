@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 #[cfg(test)]
 mod tests {
@@ -7,16 +7,120 @@ mod tests {
     #[test]
     fn test_example() {
         let input = parse_input("./src/09/test.txt");
-        assert_eq!(count_tail_locations(&input, 2), 13);
+        assert_eq!(count_tail_locations(&input, 2, 1), 13);
     }
 
     #[test]
     fn test_larger_example() {
         let input = parse_input("./src/09/test2.txt");
-        assert_eq!(count_tail_locations(&input, 10), 36);
+        assert_eq!(count_tail_locations(&input, 10, 1), 36);
+    }
+
+    #[test]
+    fn follow_from_far_away() {
+        assert_eq!(follow((0, 0), (1, -1), 1), (1, -1));
+        assert_eq!(follow((0, 0), (2, -1), 1), (1, 0));
+        assert_eq!(follow((5, 3), (0, 0), 1), (4, 3));
+        assert_eq!(follow((-7, 0), (0, 0), 1), (-6, 0));
+        assert_eq!(follow((3, 1), (0, 0), 2), (1, 1));
+        assert_eq!(follow((2, -2), (0, 0), 2), (0, 0));
+        assert_eq!(follow((2, -2), (0, 0), 0), (2, -2));
+    }
+
+    #[test]
+    fn jumps_and_diagonals() {
+        let steps = parse_input("./src/09/test3.txt");
+        assert_eq!(
+            steps,
+            vec![(-1, 1), (-1, 1), (3, 0), (3, 0), (0, -1), (2, -2)]
+        );
+        // Jumping 3 to the right from (-2, 2) drags the tail from (-1, 1) to (0, 2)
+        assert_eq!(count_tail_locations(&steps[..3], 2, 1), 3);
+        assert_eq!(count_tail_locations(&steps, 2, 1), 5);
+
+        // A long jump skips the cells in between, but the start still counts
+        let steps = [(5, 0)];
+        assert_eq!(count_tail_locations(&steps, 2, 1), 2);
+        assert_eq!(
+            RopeSimulation::new(&steps, 3, 1).next(),
+            Some(vec![(5, 0), (4, 0), (3, 0)])
+        );
+    }
+
+    #[test]
+    fn simulate_every_knot() {
+        let steps = parse_input("./src/09/test.txt");
+        let mut simulation = RopeSimulation::new(&steps, 10, 1);
+        let rope = simulation.nth(3).unwrap();
+        assert_eq!(rope[..5], [(4, 0), (3, 0), (2, 0), (1, 0), (0, 0)]);
+        let area = Bounds {
+            min: (0, 0),
+            max: (5, 4),
+        };
+        assert_eq!(
+            render_rope(&rope, &area),
+            "......\n......\n......\n......\n4321H."
+        );
+        // Four steps up from there
+        let rope = simulation.nth(3).unwrap();
+        assert_eq!(
+            render_rope(&rope, &area),
+            "....H.\n....1.\n..432.\n.5....\n6....."
+        );
+        assert_eq!(simulation.count(), steps.len() - 8);
+
+        let long_rope: Vec<(i32, i32)> = (1..=12).map(|x| (x, 1)).collect();
+        assert_eq!(
+            render_rope(&long_rope, &bounds(long_rope.clone())),
+            ".H123456789ab\ns............"
+        );
+    }
+
+    #[test]
+    fn visit_maps() {
+        let steps = parse_input("./src/09/test.txt");
+        let visits = Visits::new(RopeSimulation::new(&steps, 2, 1));
+        let tail = visits.visited(1);
+        assert_eq!(tail.len(), 13);
+        assert_eq!(
+            render_visited(
+                &tail,
+                &Bounds {
+                    min: (0, 0),
+                    max: (5, 4)
+                }
+            ),
+            "..##..\n...##.\n.####.\n....#.\ns###.."
+        );
+        assert_eq!(
+            visits.frequencies(0).values().sum::<usize>(),
+            steps.len() + 1
+        );
+        assert_eq!(visits.frequencies(1)[&(4, 3)], 6);
+
+        let frequencies = HashMap::from([((0, 0), 1), ((1, 0), 5), ((2, 0), 9), ((3, 0), 3)]);
+        assert_eq!(
+            render_heatmap(&frequencies, &bounds(frequencies.keys().copied())),
+            ".+@-"
+        );
+    }
+
+    #[test]
+    fn without_slack_the_tail_retraces_the_head() {
+        let steps = parse_input("./src/09/test2.txt");
+        let mut head = (0, 0);
+        let mut visited = HashSet::from([head]);
+        for step in &steps {
+            head = add(head, *step);
+            visited.insert(head);
+        }
+        assert_eq!(count_tail_locations(&steps, 10, 0), visited.len());
+        assert!(count_tail_locations(&steps, 10, 3) < count_tail_locations(&steps, 10, 1));
     }
 }
 
+// Every line is a direction (U, D, L, R or a diagonal like UL) and a number of steps. An
+// optional third number makes the head jump that many cells with every step.
 fn parse_input(path: &str) -> Vec<(i32, i32)> {
     let input = std::fs::read_to_string(path).unwrap();
     let lines = input.lines();
@@ -25,16 +129,19 @@ fn parse_input(path: &str) -> Vec<(i32, i32)> {
             let mut split = line.split(' ');
             let dir = split.next().unwrap();
             let amount: usize = split.next().unwrap().parse().unwrap();
+            let stride: i32 = split.next().map_or(1, |x| x.parse().unwrap());
             let delta = match dir {
                 "U" => (0, 1),
                 "D" => (0, -1),
                 "R" => (1, 0),
                 "L" => (-1, 0),
+                "UL" => (-1, 1),
+                "UR" => (1, 1),
+                "DL" => (-1, -1),
+                "DR" => (1, -1),
                 _ => panic!("invalid direction"),
             };
-            std::iter::repeat(delta)
-                .take(amount)
-                .collect::<Vec<(i32, i32)>>()
+            std::iter::repeat_n((delta.0 * stride, delta.1 * stride), amount)
         })
         .collect()
 }
@@ -47,51 +154,190 @@ fn sub(a: (i32, i32), b: (i32, i32)) -> (i32, i32) {
     (a.0 - b.0, a.1 - b.1)
 }
 
-fn follow(head: (i32, i32), tail: (i32, i32)) -> (i32, i32) {
-    let relative_position = sub(tail, head);
-    let updated_relative_position = match relative_position {
-        (-2, 0) => (-1, 0),
-        (2, 0) => (1, 0),
-        (0, -2) => (0, -1),
-        (0, 2) => (0, 1),
-        (-2, 1) | (-2, -1) => (-1, 0),
-        (2, 1) | (2, -1) => (1, 0),
-        (-1, 2) | (1, 2) => (0, 1),
-        (-1, -2) | (1, -2) => (0, -1),
-        // Add diagonal moves of the head for part 2
-        (-2, -2) => (-1, -1),
-        (-2, 2) => (-1, 1),
-        (2, 2) => (1, 1),
-        (2, -2) => (1, -1),
-        _ => relative_position,
-    };
-    add(head, updated_relative_position)
-}
-
-fn count_tail_locations(steps: &Vec<(i32, i32)>, rope_length: usize) -> usize {
-    // Create a rope of rope_length with all knots at (0, 0)
-    let mut rope: Vec<(i32, i32)> = std::iter::repeat((0, 0)).take(rope_length).collect();
-
-    let mut unique_positions = HashSet::<(i32, i32)>::new();
-    for step in steps {
-        rope[0] = add(rope[0], *step);
-        for n in 1..rope_length {
-            rope[n] = follow(rope[n - 1], rope[n]);
+// The tail stays put while it is at most `slack` cells away from the head (diagonals count
+// as one). Otherwise it steps towards the head on both axes until it is close enough again.
+// After a jump of the head the tail lands there right away, so the cells it crosses on the
+// way do not count as visited.
+fn follow(head: (i32, i32), tail: (i32, i32), slack: u32) -> (i32, i32) {
+    let mut tail = tail;
+    loop {
+        let (dx, dy) = sub(head, tail);
+        if dx.unsigned_abs().max(dy.unsigned_abs()) <= slack {
+            return tail;
+        }
+        tail = add(tail, (dx.signum(), dy.signum()));
+    }
+}
+
+// Yields the position of every knot, head first, after each step of the head
+struct RopeSimulation<'a> {
+    steps: std::slice::Iter<'a, (i32, i32)>,
+    rope: Vec<(i32, i32)>,
+    slack: u32,
+}
+
+impl<'a> RopeSimulation<'a> {
+    fn new(steps: &'a [(i32, i32)], rope_length: usize, slack: u32) -> Self {
+        RopeSimulation {
+            steps: steps.iter(),
+            // All knots start at (0, 0)
+            rope: vec![(0, 0); rope_length],
+            slack,
+        }
+    }
+
+    // Like `next`, but without copying the rope
+    fn advance(&mut self) -> Option<&[(i32, i32)]> {
+        let step = self.steps.next()?;
+        self.rope[0] = add(self.rope[0], *step);
+        for n in 1..self.rope.len() {
+            self.rope[n] = follow(self.rope[n - 1], self.rope[n], self.slack);
         }
-        let tail = *rope.last().unwrap();
-        unique_positions.insert(tail);
+        Some(&self.rope)
+    }
+}
+
+impl Iterator for RopeSimulation<'_> {
+    type Item = Vec<(i32, i32)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.advance().map(|rope| rope.to_vec())
+    }
+}
+
+// How often every knot was at each position, counting the start and the end of every step
+struct Visits {
+    counts: Vec<HashMap<(i32, i32), usize>>,
+}
+
+impl Visits {
+    fn new(simulation: RopeSimulation) -> Self {
+        let mut counts = vec![HashMap::from([((0, 0), 1)]); simulation.rope.len()];
+        for rope in simulation {
+            for (knot, position) in rope.into_iter().enumerate() {
+                *counts[knot].entry(position).or_insert(0) += 1;
+            }
+        }
+        Visits { counts }
+    }
+
+    fn visited(&self, knot: usize) -> HashSet<(i32, i32)> {
+        self.counts[knot].keys().copied().collect()
+    }
+
+    fn frequencies(&self, knot: usize) -> &HashMap<(i32, i32), usize> {
+        &self.counts[knot]
+    }
+}
+
+fn count_tail_locations(steps: &[(i32, i32)], rope_length: usize, slack: u32) -> usize {
+    let mut simulation = RopeSimulation::new(steps, rope_length, slack);
+
+    // The start counts as well, even when the first step drags the tail away from it
+    let mut unique_positions = HashSet::from([(0, 0)]);
+    while let Some(rope) = simulation.advance() {
+        unique_positions.insert(*rope.last().unwrap());
     }
 
     unique_positions.len()
 }
 
+// Inclusive corners of the area to draw, which always contains the start
+#[derive(Debug, PartialEq)]
+struct Bounds {
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+fn bounds(positions: impl IntoIterator<Item = (i32, i32)>) -> Bounds {
+    positions.into_iter().fold(
+        Bounds {
+            min: (0, 0),
+            max: (0, 0),
+        },
+        |bounds, (x, y)| Bounds {
+            min: (bounds.min.0.min(x), bounds.min.1.min(y)),
+            max: (bounds.max.0.max(x), bounds.max.1.max(y)),
+        },
+    )
+}
+
+// Up is north, like in the puzzle
+fn render(bounds: &Bounds, cell: impl Fn((i32, i32)) -> char) -> String {
+    (bounds.min.1..=bounds.max.1)
+        .rev()
+        .map(|y| {
+            (bounds.min.0..=bounds.max.0)
+                .map(|x| cell((x, y)))
+                .collect()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// `H` for the head and then 1 to 9, a to z for the following knots. A knot covers the ones
+// behind it, and any knot covers the start `s`.
+fn render_rope(rope: &[(i32, i32)], bounds: &Bounds) -> String {
+    render(bounds, |position| {
+        match rope.iter().position(|knot| *knot == position) {
+            Some(0) => 'H',
+            Some(knot) => std::char::from_digit(knot as u32, 36).unwrap_or('#'),
+            None if position == (0, 0) => 's',
+            None => '.',
+        }
+    })
+}
+
+fn render_visited(visited: &HashSet<(i32, i32)>, bounds: &Bounds) -> String {
+    render(bounds, |position| match position {
+        (0, 0) => 's',
+        _ if visited.contains(&position) => '#',
+        _ => '.',
+    })
+}
+
+// Darker characters for positions that were visited more often
+fn render_heatmap(frequencies: &HashMap<(i32, i32), usize>, bounds: &Bounds) -> String {
+    let ramp: Vec<char> = " .:-=+*#%@".chars().collect();
+    let max = frequencies.values().copied().max().unwrap_or(1);
+    let scale = (max - 1).max(1);
+    render(bounds, |position| match frequencies.get(&position) {
+        None => ramp[0],
+        Some(count) => ramp[1 + (count - 1) * (ramp.len() - 2) / scale],
+    })
+}
+
 fn main() {
     println!("Hello, day 9!");
     let input = parse_input("./input/09/input.txt");
 
-    let unique_positions = count_tail_locations(&input, 2);
+    // `cargo run --bin 09 -- rope <knots> <step>` draws the rope after a step, and
+    // `cargo run --bin 09 -- <visited|heatmap> <knots> [knot]` shows where a knot (the tail) went
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(mode @ ("rope" | "visited" | "heatmap")) = args.get(1).map(String::as_str) {
+        let rope_length: usize = args.get(2).map_or(10, |x| x.parse().unwrap());
+        let area = bounds(RopeSimulation::new(&input, rope_length, 1).flatten());
+        if mode == "rope" {
+            let step: usize = args.get(3).map_or(1, |x| x.parse().unwrap());
+            let rope = RopeSimulation::new(&input, rope_length, 1)
+                .nth(step.checked_sub(1).expect("steps start at 1"))
+                .expect("step out of range");
+            println!("{}", render_rope(&rope, &area));
+        } else {
+            let knot = args.get(3).map_or(rope_length - 1, |x| x.parse().unwrap());
+            let visits = Visits::new(RopeSimulation::new(&input, rope_length, 1));
+            if mode == "visited" {
+                println!("{}", render_visited(&visits.visited(knot), &area));
+            } else {
+                println!("{}", render_heatmap(visits.frequencies(knot), &area));
+            }
+        }
+        return;
+    }
+
+    let unique_positions = count_tail_locations(&input, 2, 1);
     println!("Part 1: {}", unique_positions);
 
-    let unique_positions = count_tail_locations(&input, 10);
+    let unique_positions = count_tail_locations(&input, 10, 1);
     println!("Part 2: {}", unique_positions);
 }